@@ -0,0 +1,60 @@
+use serenity::{
+	client::Context,
+	framework::standard::{macros::command, Args, CommandResult},
+	model::channel::Message,
+};
+
+use super::helpers::{is_dj, CommandSource};
+use crate::loads;
+
+#[command]
+#[only_in(guilds)]
+#[max_args(1)]
+#[usage("[load]")]
+#[example("3")]
+/// Stops loading songs into the queue. Without a load number, every load you
+/// started is cancelled, or every load in the server for DJs
+async fn cancel(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
+	if args.is_empty() {
+		return cancel_loads(ctx, &msg.into(), None).await;
+	}
+
+	match args.single::<u64>() {
+		Ok(id) => cancel_loads(ctx, &msg.into(), Some(id)).await,
+		Err(_) => {
+			msg.channel_id
+				.say(&ctx.http, "The load must be given as a number.")
+				.await?;
+			Ok(())
+		}
+	}
+}
+
+pub(crate) async fn cancel_loads(
+	ctx: &Context,
+	source: &CommandSource<'_>,
+	id: Option<u64>,
+) -> CommandResult {
+	let guild_id = source.guild_id().unwrap();
+	let author = source.author().id;
+
+	// DJs can cancel loads started by anyone
+	let user = if is_dj(ctx, guild_id, author).await {
+		None
+	} else {
+		Some(author)
+	};
+
+	let cancelled = loads::cancel(&ctx.data, guild_id, id, user).await;
+	let reply = match (id, cancelled.len()) {
+		(Some(id), 0) => format!("There is no load {} you can cancel.", id),
+		(Some(id), _) => format!("Cancelled load {}.", id),
+		(None, 0) => {
+			"There is nothing loading that you can cancel.".to_string()
+		}
+		(None, count) => format!("Cancelled {} load(s).", count),
+	};
+	source.say(ctx, reply).await?;
+
+	Ok(())
+}
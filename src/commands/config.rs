@@ -0,0 +1,162 @@
+use serenity::{
+	client::Context,
+	framework::standard::{macros::command, Args, CommandResult},
+	model::channel::Message,
+	utils::MessageBuilder,
+};
+use tracing::error;
+
+use super::{
+	helpers::CommandSource, mode::apply_mode, stay::apply_always_on,
+	volume::apply_volume,
+};
+use crate::settings::{self, ConfigError, GuildSettings, KEYS};
+
+#[command]
+#[only_in(guilds)]
+#[num_args(0)]
+#[required_permissions("MANAGE_GUILD")]
+#[sub_commands(config_get, config_set, config_reset)]
+/// Shows every setting of the server, which can be changed with `config set`
+async fn config(ctx: &Context, msg: &Message) -> CommandResult {
+	show_config(ctx, &msg.into(), None).await
+}
+
+#[command("get")]
+#[only_in(guilds)]
+#[max_args(1)]
+#[required_permissions("MANAGE_GUILD")]
+#[usage("[setting]")]
+#[example("prefix")]
+/// Shows one setting of the server, or all of them
+async fn config_get(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
+	let key = args.message().trim();
+	show_config(ctx, &msg.into(), (!key.is_empty()).then(|| key)).await
+}
+
+#[command("set")]
+#[only_in(guilds)]
+#[min_args(2)]
+#[required_permissions("MANAGE_GUILD")]
+#[usage("setting value")]
+#[example("prefix !")]
+#[example("max-queue 100")]
+#[example("dj-role @DJ")]
+#[example("text-channels #music #bots")]
+/// Changes a setting of the server. The settings are `prefix`, `dj-role`,
+/// `volume`, `max-queue`, `skip-ratio`, `mode`, `24-7`, `text-channels` and
+/// `voice-channels`
+async fn config_set(
+	ctx: &Context,
+	msg: &Message,
+	mut args: Args,
+) -> CommandResult {
+	let key = args.single::<String>()?;
+	set_config(ctx, &msg.into(), &key, args.rest().trim()).await
+}
+
+#[command("reset")]
+#[only_in(guilds)]
+#[num_args(1)]
+#[required_permissions("MANAGE_GUILD")]
+#[usage("setting")]
+#[example("prefix")]
+/// Puts a setting of the server back to its default
+async fn config_reset(
+	ctx: &Context,
+	msg: &Message,
+	args: Args,
+) -> CommandResult {
+	reset_config(ctx, &msg.into(), args.message().trim()).await
+}
+
+pub(crate) async fn show_config(
+	ctx: &Context,
+	source: &CommandSource<'_>,
+	key: Option<&str>,
+) -> CommandResult {
+	let settings = settings::get(&ctx.data, source.guild_id().unwrap()).await;
+
+	let mut message = MessageBuilder::new();
+	for key in key.map_or(KEYS.to_vec(), |key| vec![key]) {
+		match settings.get_value(key) {
+			Ok(value) => {
+				message.push_mono(key).push(": ").push_line(value);
+			}
+			Err(e) => {
+				source.say(ctx, e).await?;
+				return Ok(());
+			}
+		}
+	}
+
+	source.say_embed(ctx, message).await?;
+
+	Ok(())
+}
+
+pub(crate) async fn set_config(
+	ctx: &Context,
+	source: &CommandSource<'_>,
+	key: &str,
+	value: &str,
+) -> CommandResult {
+	change_config(ctx, source, key, |settings| settings.set_value(key, value))
+		.await
+}
+
+pub(crate) async fn reset_config(
+	ctx: &Context,
+	source: &CommandSource<'_>,
+	key: &str,
+) -> CommandResult {
+	change_config(ctx, source, key, |settings| settings.reset_value(key)).await
+}
+
+/// Saves a change to a setting, then applies it to whatever is playing.
+async fn change_config(
+	ctx: &Context,
+	source: &CommandSource<'_>,
+	key: &str,
+	change: impl FnOnce(&mut GuildSettings) -> Result<(), ConfigError>,
+) -> CommandResult {
+	let guild_id = source.guild_id().unwrap();
+
+	// nothing is saved if the change fails
+	let (previous, changed) =
+		match settings::try_update(&ctx.data, guild_id, change).await {
+			Ok(Ok(settings)) => settings,
+			Ok(Err(e)) => {
+				source.say(ctx, e).await?;
+				return Ok(());
+			}
+			Err(e) => {
+				error!("Could not save settings of {}: {}", guild_id, e);
+				source.say(ctx, "Could not save the setting.").await?;
+				return Ok(());
+			}
+		};
+
+	match key {
+		"volume" => {
+			apply_volume(ctx, guild_id, changed.volume_multiplier()).await
+		}
+		"mode" => apply_mode(ctx, guild_id, previous.mode, changed.mode).await,
+		"24-7" => apply_always_on(ctx, guild_id, changed.always_on).await,
+		_ => {}
+	}
+
+	let value = changed.get_value(key).unwrap_or_default();
+	source
+		.say_embed(
+			ctx,
+			MessageBuilder::new()
+				.push_mono(key)
+				.push(" is now ")
+				.push(value)
+				.build(),
+		)
+		.await?;
+
+	Ok(())
+}
@@ -0,0 +1,99 @@
+use serenity::{
+	client::Context,
+	framework::standard::{macros::command, Args, CommandResult},
+	model::{channel::Message, id::RoleId, misc::Mentionable},
+	utils::parse_role,
+};
+use tracing::error;
+
+use super::helpers::{can_manage, CommandSource};
+use crate::settings;
+
+#[command]
+#[only_in(guilds)]
+#[max_args(1)]
+#[usage("[@role | none]")]
+#[example("@DJ")]
+#[example("none")]
+/// Shows or changes the DJ role. When one is set, members without it can only
+/// skip and remove their own songs, have to vote to skip others, and can't
+/// change the playback, the order of the queue or the settings. Changing the
+/// role needs the Manage Server permission
+async fn dj(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
+	let input = args.message().trim();
+	if input.is_empty() {
+		return set_dj_role(ctx, &msg.into(), None).await;
+	}
+
+	let role = if input.eq_ignore_ascii_case("none") {
+		Some(None)
+	} else {
+		parse_role(input)
+			.or_else(|| input.parse().ok())
+			.map(|role| Some(RoleId(role)))
+	};
+
+	let allowed = match msg.member(ctx).await {
+		Ok(member) => can_manage(ctx, &member).await,
+		Err(_) => false,
+	};
+
+	match role {
+		Some(_) if !allowed => {
+			msg.channel_id
+				.say(
+					&ctx.http,
+					"Only members that can manage the server can do that.",
+				)
+				.await?;
+			Ok(())
+		}
+		Some(role) => set_dj_role(ctx, &msg.into(), Some(role)).await,
+		None => {
+			msg.channel_id
+				.say(&ctx.http, "Mention a role, or use `none` to remove it.")
+				.await?;
+			Ok(())
+		}
+	}
+}
+
+/// Shows the DJ role when `role` is `None`, otherwise changes it, with
+/// `Some(None)` removing it.
+pub(crate) async fn set_dj_role(
+	ctx: &Context,
+	source: &CommandSource<'_>,
+	role: Option<Option<RoleId>>,
+) -> CommandResult {
+	let guild_id = source.guild_id().unwrap();
+
+	let role = match role {
+		Some(role) => role,
+		None => {
+			let role = settings::get(&ctx.data, guild_id).await.dj_role;
+			source.say(ctx, describe(role)).await?;
+			return Ok(());
+		}
+	};
+
+	if let Err(e) = settings::update(&ctx.data, guild_id, |settings| {
+		settings.dj_role = role
+	})
+	.await
+	{
+		error!("Could not save settings of {}: {}", guild_id, e);
+		source.say(ctx, "Could not save the DJ role.").await?;
+		return Ok(());
+	}
+
+	source.say(ctx, describe(role)).await?;
+
+	Ok(())
+}
+
+fn describe(role: Option<RoleId>) -> String {
+	match role {
+		Some(role) => format!("The DJ role is {}.", role.mention()),
+		None => "There is no DJ role, everyone has full control.".to_string(),
+	}
+}
@@ -1,29 +1,58 @@
+mod permissions;
+mod source;
+
 pub(crate) use crate::join_channel;
+pub(crate) use permissions::*;
+pub(crate) use source::*;
 
 #[macro_export]
 macro_rules! join_channel {
-	($ctx:ident, $msg:ident) => {{
-		use crate::utils::get_user_server_channel;
+	($ctx:ident, $source:ident) => {
+		$crate::join_channel!($ctx, $source, offer_restore = true)
+	};
+	($ctx:ident, $source:ident, offer_restore = $offer_restore:expr) => {{
 		use tracing::error;
+		use $crate::utils::get_user_server_channel;
 
-		let (guild_id, channel_id) =
-			match get_user_server_channel($ctx, $msg).await {
-				Some(channel) => channel,
-				None => {
-					$msg.reply(
-						&$ctx.http,
+		let (guild_id, channel_id) = match get_user_server_channel(
+			$ctx,
+			$source.guild_id(),
+			$source.author().id,
+		)
+		.await
+		{
+			Some(channel) => channel,
+			None => {
+				$source
+					.reply(
+						$ctx,
 						"You must be in a voice channel to use this command.",
 					)
 					.await?;
-					return Ok(());
-				}
-			};
+				return Ok(());
+			}
+		};
 
 		let manager = songbird::get($ctx)
 			.await
 			.expect("Unable to obtain Songbird client")
 			.clone();
 
+		if manager.get(guild_id).is_none() {
+			use $crate::commands::helpers::check_voice_channel;
+			if let Err(reason) =
+				check_voice_channel($ctx, guild_id, channel_id).await
+			{
+				$source.reply($ctx, reason).await?;
+				return Ok(());
+			}
+		}
+
+		use $crate::idle::{Idle, IdleReason};
+		let idle = Idle::from_context($ctx).await;
+		// the queue is about to be added to, so don't leave while it loads
+		idle.stop(guild_id, IdleReason::QueueEmpty).await;
+
 		match manager.get(guild_id) {
 			Some(handler_lock) => handler_lock,
 			None => {
@@ -32,26 +61,37 @@ macro_rules! join_channel {
 
 				match success {
 					Ok(_) => {
-						use crate::events::TrackEnd;
 						use songbird::Event;
+						use $crate::events::TrackEnd;
 						let mut handler = handler_lock.lock().await;
 						handler.deafen(true).await?;
 						handler.add_global_event(
 							Event::Track(songbird::TrackEvent::End),
-							TrackEnd {
-								guild_id,
-								manager: manager.clone(),
-							},
+							TrackEnd { guild_id, idle },
 						);
 					}
 					Err(e) => {
-						$msg.reply(&$ctx.http, "Error joining the channel.")
+						error!("Cannot join channel {}: {:?}", channel_id, e);
+						$source
+							.reply($ctx, $crate::error::Error::from(e))
 							.await?;
-						error!("Cannot join channel: {:?}", e);
 						return Ok(());
 					}
 				}
 
+				if $offer_restore {
+					use $crate::snapshot::QueueSnapshot;
+					if let Ok(Some(_)) = QueueSnapshot::load(guild_id).await {
+						$source
+							.say(
+								$ctx,
+								"A queue saved before the bot restarted is \
+								 available, use `restore` to bring it back.",
+							)
+							.await?;
+					}
+				}
+
 				handler_lock
 			}
 		}
@@ -0,0 +1,175 @@
+use serenity::{
+	client::Context,
+	framework::standard::CommandResult,
+	model::{
+		guild::Member,
+		id::{ChannelId, GuildId, UserId},
+		misc::Mentionable,
+	},
+};
+
+use super::CommandSource;
+use crate::{settings, utils::get_listeners};
+
+/// The people in the voice channel the bot is playing in.
+pub(crate) async fn get_bot_listeners(
+	ctx: &Context,
+	guild: GuildId,
+) -> Vec<UserId> {
+	let manager = songbird::get(ctx)
+		.await
+		.expect("Songbird Voice Client placed in at initialisation.")
+		.clone();
+
+	let channel = match manager.get(guild) {
+		Some(handler_lock) => handler_lock.lock().await.current_channel(),
+		None => None,
+	};
+
+	match channel {
+		Some(channel) => {
+			get_listeners(&ctx.cache, guild, ChannelId(channel.0)).await
+		}
+		None => Vec::new(),
+	}
+}
+
+/// Checks whether a user has full control over playback, which is the case
+/// when the guild has no DJ role, when they have the role or can manage the
+/// guild, or when nobody else is listening.
+pub(crate) async fn is_dj(ctx: &Context, guild: GuildId, user: UserId) -> bool {
+	let dj_role = match settings::get(&ctx.data, guild).await.dj_role {
+		Some(dj_role) => dj_role,
+		None => return true,
+	};
+
+	if let Ok(member) = guild.member(ctx, user).await {
+		if member.roles.contains(&dj_role) || can_manage(ctx, &member).await {
+			return true;
+		}
+	}
+
+	get_bot_listeners(ctx, guild).await == [user]
+}
+
+/// Checks whether the member can manage the guild.
+pub(crate) async fn can_manage(ctx: &Context, member: &Member) -> bool {
+	member
+		.permissions(ctx)
+		.await
+		.map_or(false, |permissions| permissions.manage_guild())
+}
+
+/// Checks that a command was used in one of the text channels that the guild
+/// has bound the bot to, returning where it can be used if not. Members that
+/// can manage the guild can use commands anywhere, so they can always change
+/// the channels.
+pub(crate) async fn check_text_channel(
+	ctx: &Context,
+	guild: GuildId,
+	channel: ChannelId,
+	user: UserId,
+) -> Result<(), String> {
+	let channels = settings::get(&ctx.data, guild).await.text_channels;
+	if is_allowed(&channels, channel) {
+		return Ok(());
+	}
+
+	if let Ok(member) = guild.member(ctx, user).await {
+		if can_manage(ctx, &member).await {
+			return Ok(());
+		}
+	}
+
+	Err(format!(
+		"Music commands can only be used in {}.",
+		list_channels(&channels)
+	))
+}
+
+/// Checks that the bot is allowed to join a voice channel, returning the
+/// channels it can join if not.
+pub(crate) async fn check_voice_channel(
+	ctx: &Context,
+	guild: GuildId,
+	channel: ChannelId,
+) -> Result<(), String> {
+	let channels = settings::get(&ctx.data, guild).await.voice_channels;
+	if is_allowed(&channels, channel) {
+		return Ok(());
+	}
+
+	Err(format!(
+		"The bot can only join {}.",
+		list_channels(&channels)
+	))
+}
+
+/// Whether a channel is allowed, where no channels allows any of them.
+fn is_allowed(channels: &[ChannelId], channel: ChannelId) -> bool {
+	channels.is_empty() || channels.contains(&channel)
+}
+
+fn list_channels(channels: &[ChannelId]) -> String {
+	let mentions = channels
+		.iter()
+		.map(|channel| channel.mention().to_string())
+		.collect::<Vec<_>>();
+
+	match mentions.split_last() {
+		Some((last, rest)) if !rest.is_empty() => {
+			format!("{} or {}", rest.join(", "), last)
+		}
+		_ => mentions.join(""),
+	}
+}
+
+/// Checks that the author of a command is a DJ, explaining that the action is
+/// not allowed if they aren't.
+pub(crate) async fn require_dj(
+	ctx: &Context,
+	source: &CommandSource<'_>,
+	action: &str,
+) -> CommandResult<bool> {
+	let guild = source.guild_id().unwrap();
+	if is_dj(ctx, guild, source.author().id).await {
+		return Ok(true);
+	}
+
+	source
+		.say(
+			ctx,
+			format!("Only members with the DJ role can {}.", action),
+		)
+		.await?;
+
+	Ok(false)
+}
+
+#[cfg(test)]
+mod tests {
+	use serenity::model::id::ChannelId;
+
+	use super::{is_allowed, list_channels};
+
+	#[test]
+	fn test_is_allowed() {
+		assert!(is_allowed(&[], ChannelId(1)));
+		assert!(is_allowed(&[ChannelId(1), ChannelId(2)], ChannelId(2)));
+		assert!(!is_allowed(&[ChannelId(1), ChannelId(2)], ChannelId(3)));
+	}
+
+	#[test]
+	fn test_list_channels() {
+		assert_eq!(list_channels(&[]), "");
+		assert_eq!(list_channels(&[ChannelId(1)]), "<#1>");
+		assert_eq!(
+			list_channels(&[ChannelId(1), ChannelId(2)]),
+			"<#1> or <#2>"
+		);
+		assert_eq!(
+			list_channels(&[ChannelId(1), ChannelId(2), ChannelId(3)]),
+			"<#1>, <#2> or <#3>"
+		);
+	}
+}
@@ -0,0 +1,311 @@
+use std::{
+	fmt::Display,
+	sync::atomic::{AtomicBool, Ordering},
+};
+
+use serenity::{
+	builder::CreateEmbed,
+	client::Context,
+	model::{
+		channel::Message,
+		id::{ChannelId, GuildId},
+		interactions::application_command::ApplicationCommandInteraction,
+		user::User,
+	},
+	Result as SerenityResult,
+};
+
+use crate::utils::Requester;
+
+/// Where a command was invoked from, so the same command logic can answer both
+/// prefix commands and slash commands.
+pub(crate) enum CommandSource<'a> {
+	Message(&'a Message),
+	/// A deferred slash command. `responded` is set once the original response
+	/// has been filled in, after which further messages are sent as followups.
+	Interaction {
+		interaction: &'a ApplicationCommandInteraction,
+		responded: AtomicBool,
+	},
+}
+
+impl<'a> From<&'a Message> for CommandSource<'a> {
+	fn from(msg: &'a Message) -> Self {
+		Self::Message(msg)
+	}
+}
+
+impl<'a> From<&'a ApplicationCommandInteraction> for CommandSource<'a> {
+	fn from(interaction: &'a ApplicationCommandInteraction) -> Self {
+		Self::Interaction {
+			interaction,
+			responded: AtomicBool::new(false),
+		}
+	}
+}
+
+impl<'a> CommandSource<'a> {
+	pub(crate) fn guild_id(&self) -> Option<GuildId> {
+		match self {
+			Self::Message(msg) => msg.guild_id,
+			Self::Interaction { interaction, .. } => interaction.guild_id,
+		}
+	}
+
+	pub(crate) fn author(&self) -> &User {
+		match self {
+			Self::Message(msg) => &msg.author,
+			Self::Interaction { interaction, .. } => &interaction.user,
+		}
+	}
+
+	pub(crate) fn channel_id(&self) -> ChannelId {
+		match self {
+			Self::Message(msg) => msg.channel_id,
+			Self::Interaction { interaction, .. } => interaction.channel_id,
+		}
+	}
+
+	/// Who to record as the requester of tracks queued by this command.
+	pub(crate) fn requester(&self) -> Requester {
+		Requester {
+			user: self.author().id,
+			channel: self.channel_id(),
+		}
+	}
+
+	/// Whether anything has been sent in response to the command yet.
+	pub(crate) fn has_responded(&self) -> bool {
+		match self {
+			Self::Message(_) => true,
+			Self::Interaction { responded, .. } => {
+				responded.load(Ordering::Acquire)
+			}
+		}
+	}
+
+	pub(crate) async fn say(
+		&self,
+		ctx: &Context,
+		content: impl Display,
+	) -> SerenityResult<()> {
+		self.status(ctx, content).await.map(|_| ())
+	}
+
+	/// Like [`say`](Self::say), but mentions the user for prefix commands.
+	pub(crate) async fn reply(
+		&self,
+		ctx: &Context,
+		content: impl Display,
+	) -> SerenityResult<()> {
+		match self {
+			Self::Message(msg) => {
+				msg.reply(&ctx.http, content).await.map(|_| ())
+			}
+			Self::Interaction { .. } => self.say(ctx, content).await,
+		}
+	}
+
+	pub(crate) async fn say_embed(
+		&self,
+		ctx: &Context,
+		description: impl ToString,
+	) -> SerenityResult<()> {
+		let mut embed = CreateEmbed::default();
+		embed.description(description);
+
+		match self {
+			Self::Message(msg) => msg
+				.channel_id
+				.send_message(&ctx.http, |m| m.set_embed(embed))
+				.await
+				.map(|_| ()),
+			Self::Interaction {
+				interaction,
+				responded,
+			} => {
+				if responded.swap(true, Ordering::AcqRel) {
+					interaction
+						.create_followup_message(&ctx.http, |m| {
+							m.add_embed(embed)
+						})
+						.await
+						.map(|_| ())
+				} else {
+					interaction
+						.edit_original_interaction_response(&ctx.http, |m| {
+							m.add_embed(embed)
+						})
+						.await
+						.map(|_| ())
+				}
+			}
+		}
+	}
+
+	/// Sends a file along with a message. Followups to slash commands can't
+	/// hold files, so for those the file is sent to the channel after the
+	/// message.
+	pub(crate) async fn send_file(
+		&self,
+		ctx: &Context,
+		content: impl Display,
+		filename: &str,
+		data: &[u8],
+	) -> SerenityResult<()> {
+		match self {
+			Self::Message(msg) => msg
+				.channel_id
+				.send_files(&ctx.http, vec![(data, filename)], |m| {
+					m.content(content)
+				})
+				.await
+				.map(|_| ()),
+			Self::Interaction { interaction, .. } => {
+				self.say(ctx, content).await?;
+				interaction
+					.channel_id
+					.send_files(&ctx.http, vec![(data, filename)], |m| m)
+					.await
+					.map(|_| ())
+			}
+		}
+	}
+
+	/// Sends a message that can later be edited to show the command's result.
+	pub(crate) async fn status(
+		&self,
+		ctx: &Context,
+		content: impl Display,
+	) -> SerenityResult<StatusMessage<'a>> {
+		match self {
+			Self::Message(msg) => msg
+				.channel_id
+				.say(&ctx.http, content)
+				.await
+				.map(StatusMessage::Message),
+			Self::Interaction {
+				interaction,
+				responded,
+			} => {
+				if responded.swap(true, Ordering::AcqRel) {
+					interaction
+						.create_followup_message(&ctx.http, |m| {
+							m.content(content)
+						})
+						.await
+						.map(|message| {
+							StatusMessage::Followup(interaction, message)
+						})
+				} else {
+					interaction
+						.edit_original_interaction_response(&ctx.http, |m| {
+							m.content(content)
+						})
+						.await
+						.map(|message| {
+							StatusMessage::Response(interaction, message)
+						})
+				}
+			}
+		}
+	}
+}
+
+/// A message sent through [`CommandSource::status`].
+pub(crate) enum StatusMessage<'a> {
+	Message(Message),
+	Response(&'a ApplicationCommandInteraction, Message),
+	Followup(&'a ApplicationCommandInteraction, Message),
+}
+
+impl StatusMessage<'_> {
+	/// Whether the message was sent for a slash command, which can only edit
+	/// it for 15 minutes.
+	pub(crate) fn expires(&self) -> bool {
+		!matches!(self, Self::Message(_))
+	}
+
+	pub(crate) fn message(&self) -> &Message {
+		match self {
+			Self::Message(message)
+			| Self::Response(_, message)
+			| Self::Followup(_, message) => message,
+		}
+	}
+
+	/// Replaces the text of the message, leaving any embed in place.
+	pub(crate) async fn edit_text(
+		&mut self,
+		ctx: &Context,
+		content: impl ToString,
+	) -> SerenityResult<()> {
+		match self {
+			Self::Message(message) => {
+				message.edit(&ctx.http, |m| m.content(content)).await
+			}
+			Self::Response(interaction, message) => {
+				*message = interaction
+					.edit_original_interaction_response(&ctx.http, |m| {
+						m.content(content)
+					})
+					.await?;
+				Ok(())
+			}
+			Self::Followup(interaction, message) => {
+				*message = interaction
+					.edit_followup_message(&ctx.http, message.id, |m| {
+						m.content(content)
+					})
+					.await?;
+				Ok(())
+			}
+		}
+	}
+
+	/// Replaces the text and embed of the message.
+	pub(crate) async fn edit_embed(
+		&mut self,
+		ctx: &Context,
+		content: impl ToString,
+		description: impl ToString,
+	) -> SerenityResult<()> {
+		let mut embed = CreateEmbed::default();
+		embed.description(description);
+
+		self.edit_rich_embed(ctx, content, embed).await
+	}
+
+	/// Like [`edit_embed`](Self::edit_embed), for embeds with more than a
+	/// description.
+	pub(crate) async fn edit_rich_embed(
+		&mut self,
+		ctx: &Context,
+		content: impl ToString,
+		embed: CreateEmbed,
+	) -> SerenityResult<()> {
+		match self {
+			Self::Message(message) => {
+				message
+					.edit(&ctx.http, |m| m.content(content).set_embed(embed))
+					.await
+			}
+			Self::Response(interaction, message) => {
+				*message = interaction
+					.edit_original_interaction_response(&ctx.http, |m| {
+						m.content(content).set_embeds(vec![embed])
+					})
+					.await?;
+				Ok(())
+			}
+			Self::Followup(interaction, message) => {
+				*message = interaction
+					.edit_followup_message(&ctx.http, message.id, |m| {
+						m.content(content).embeds(vec![embed])
+					})
+					.await?;
+				Ok(())
+			}
+		}
+	}
+}
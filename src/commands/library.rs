@@ -0,0 +1,247 @@
+use serenity::{
+	client::Context,
+	framework::standard::{macros::command, Args, CommandResult},
+	model::channel::Message,
+	utils::MessageBuilder,
+};
+
+use super::helpers::{join_channel, CommandSource};
+use crate::{
+	library::{self, LibraryTrack},
+	loads::LoadJob,
+	utils::{format_duration, leave_if_empty, PlayParameter},
+};
+
+/// Number of tracks shown on each page of the library listing.
+const PAGE_SIZE: usize = 20;
+
+/// Number of tracks shown in library search results.
+const SEARCH_LIMIT: usize = 10;
+
+#[command]
+#[only_in(guilds)]
+#[max_args(1)]
+#[sub_commands(library_list, library_search, library_play)]
+#[usage("[page]")]
+/// Lists the tracks in the bot's music library
+async fn library(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
+	library_list(ctx, msg, args).await
+}
+
+#[command("list")]
+#[only_in(guilds)]
+#[max_args(1)]
+#[usage("[page]")]
+/// Lists the tracks in the bot's music library
+async fn library_list(
+	ctx: &Context,
+	msg: &Message,
+	mut args: Args,
+) -> CommandResult {
+	if args.is_empty() {
+		return list_library(ctx, &msg.into(), 1).await;
+	}
+
+	match args.single::<usize>() {
+		Ok(page) => list_library(ctx, &msg.into(), page).await,
+		Err(_) => {
+			msg.channel_id
+				.say(&ctx.http, "Page must be a positive number.")
+				.await?;
+			Ok(())
+		}
+	}
+}
+
+#[command("search")]
+#[only_in(guilds)]
+#[min_args(1)]
+/// Searches the music library by title, artist, album and file name
+async fn library_search(
+	ctx: &Context,
+	msg: &Message,
+	args: Args,
+) -> CommandResult {
+	search_library(ctx, &msg.into(), args.message().trim()).await
+}
+
+#[command("play")]
+#[only_in(guilds)]
+#[min_args(1)]
+#[usage("track-number | path | search")]
+#[example("12")]
+#[example("albums/Some Album")]
+#[example("some song")]
+/// Plays a track from the music library, given its number in the listing,
+/// its path in the library, which may be a whole directory, or search terms
+async fn library_play(
+	ctx: &Context,
+	msg: &Message,
+	args: Args,
+) -> CommandResult {
+	play_from_library(ctx, &msg.into(), args.message().trim()).await
+}
+
+pub(crate) async fn list_library(
+	ctx: &Context,
+	source: &CommandSource<'_>,
+	page: usize,
+) -> CommandResult {
+	let library = match library::get(ctx).await {
+		Some(library) => library,
+		None => {
+			source.say(ctx, "No music library is configured.").await?;
+			return Ok(());
+		}
+	};
+
+	let tracks = library.tracks();
+	let page_count = (tracks.len() + PAGE_SIZE - 1) / PAGE_SIZE;
+	if tracks.is_empty() {
+		source
+			.say(ctx, "The music library is empty, or still being indexed.")
+			.await?;
+		return Ok(());
+	} else if page == 0 || page > page_count {
+		source
+			.say(ctx, format!("Page must be between 1 and {}.", page_count))
+			.await?;
+		return Ok(());
+	}
+
+	let mut message = MessageBuilder::new();
+	tracks
+		.iter()
+		.enumerate()
+		.skip((page - 1) * PAGE_SIZE)
+		.take(PAGE_SIZE)
+		.for_each(|(index, track)| push_track(&mut message, index + 1, track));
+	message.push(format!(
+		"\nPage {} of {}, {} track(s)",
+		page,
+		page_count,
+		tracks.len()
+	));
+
+	source.say_embed(ctx, message).await?;
+
+	Ok(())
+}
+
+pub(crate) async fn search_library(
+	ctx: &Context,
+	source: &CommandSource<'_>,
+	query: &str,
+) -> CommandResult {
+	let library = match library::get(ctx).await {
+		Some(library) => library,
+		None => {
+			source.say(ctx, "No music library is configured.").await?;
+			return Ok(());
+		}
+	};
+
+	let results = library.search(query);
+	if results.is_empty() {
+		source
+			.say(
+				ctx,
+				MessageBuilder::new()
+					.push("No results found for ")
+					.push_quote_safe(query),
+			)
+			.await?;
+		return Ok(());
+	}
+
+	let mut message = MessageBuilder::new();
+	results
+		.iter()
+		.take(SEARCH_LIMIT)
+		.for_each(|(number, track)| push_track(&mut message, *number, track));
+	if results.len() > SEARCH_LIMIT {
+		message.push_line(format!("and {} more", results.len() - SEARCH_LIMIT));
+	}
+	message.push("\nUse `library play <number>` to play a track.");
+
+	source.say_embed(ctx, message).await?;
+
+	Ok(())
+}
+
+pub(crate) async fn play_from_library(
+	ctx: &Context,
+	source: &CommandSource<'_>,
+	query: &str,
+) -> CommandResult {
+	let library = match library::get(ctx).await {
+		Some(library) => library,
+		None => {
+			source.say(ctx, "No music library is configured.").await?;
+			return Ok(());
+		}
+	};
+
+	let tracks = match library.find(query).await {
+		Ok(tracks) => tracks,
+		Err(e) => {
+			source.say(ctx, e).await?;
+			return Ok(());
+		}
+	};
+
+	let handler_lock = join_channel!(ctx, source);
+	let mut result_message =
+		source.status(ctx, "Please wait, loading...").await?;
+
+	let mut load = LoadJob::start(ctx, source, &mut result_message).await;
+	let total = tracks.len();
+	let song_stream =
+		PlayParameter::Tracks(tracks).get_tracks(library.resolver());
+	match load
+		.queue(
+			ctx,
+			source,
+			&mut result_message,
+			&*handler_lock,
+			song_stream,
+			Some(total),
+		)
+		.await
+	{
+		Ok(message) => {
+			result_message.edit_embed(ctx, "", message).await?;
+		}
+		Err(e) => {
+			result_message.edit_text(ctx, e).await?;
+			leave_if_empty(
+				ctx,
+				handler_lock.lock().await,
+				source.guild_id().unwrap(),
+			)
+			.await;
+		}
+	}
+
+	Ok(())
+}
+
+fn push_track(
+	message: &mut MessageBuilder,
+	number: usize,
+	track: &LibraryTrack,
+) {
+	message
+		.push_mono(number)
+		.push(" | ")
+		.push_safe(track.name())
+		.push("  ")
+		.push_mono_line(
+			track
+				.descriptor
+				.duration
+				.map(format_duration)
+				.as_deref()
+				.unwrap_or("No info"),
+		);
+}
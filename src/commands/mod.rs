@@ -1,16 +1,29 @@
 pub mod about;
+pub mod cancel;
+pub mod config;
+pub mod dj;
 pub mod help;
+pub mod library;
+pub mod mode;
+pub mod nowplaying;
 pub mod pause;
 pub mod ping;
 pub mod play;
+pub mod playlist;
 pub mod playnext;
 pub mod queue;
+pub mod remove;
+pub mod reorder;
 pub mod repeat;
+pub mod restore;
 pub mod resume;
 pub mod search;
+pub mod seek;
 pub mod shuffle;
 pub mod skip;
+pub mod stay;
 pub mod stop;
 pub mod version;
+pub mod volume;
 
-mod helpers;
+pub(crate) mod helpers;
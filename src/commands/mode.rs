@@ -0,0 +1,97 @@
+use serenity::{
+	client::Context,
+	framework::standard::{macros::command, Args, CommandResult},
+	model::{channel::Message, id::GuildId},
+};
+use tracing::error;
+
+use super::helpers::{require_dj, CommandSource};
+use crate::settings::{self, PlaybackMode};
+
+#[command]
+#[only_in(guilds)]
+#[max_args(1)]
+#[usage("[normal|queue|track|autoplay]")]
+#[example("queue")]
+/// Shows or changes what happens as songs end: `normal` plays each song once,
+/// `queue` loops the whole queue, `track` repeats the current song, and
+/// `autoplay` adds a related song when the queue runs out
+async fn mode(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
+	if args.is_empty() {
+		return set_mode(ctx, &msg.into(), None).await;
+	}
+
+	match args.message().trim().parse::<PlaybackMode>() {
+		Ok(mode) => set_mode(ctx, &msg.into(), Some(mode)).await,
+		Err(_) => {
+			msg.channel_id
+				.say(
+					&ctx.http,
+					"Mode must be `normal`, `queue`, `track` or `autoplay`.",
+				)
+				.await?;
+			Ok(())
+		}
+	}
+}
+
+pub(crate) async fn set_mode(
+	ctx: &Context,
+	source: &CommandSource<'_>,
+	mode: Option<PlaybackMode>,
+) -> CommandResult {
+	let guild_id = source.guild_id().unwrap();
+
+	let mode = match mode {
+		Some(mode) => mode,
+		None => {
+			let mode = settings::get(&ctx.data, guild_id).await.mode;
+			source.say(ctx, format!("Mode is {}.", mode)).await?;
+			return Ok(());
+		}
+	};
+
+	if !require_dj(ctx, source, "change the mode").await? {
+		return Ok(());
+	}
+
+	let previous = settings::get(&ctx.data, guild_id).await.mode;
+	if let Err(e) =
+		settings::update(&ctx.data, guild_id, |settings| settings.mode = mode)
+			.await
+	{
+		error!("Could not save settings of {}: {}", guild_id, e);
+		source.say(ctx, "Could not save the mode.").await?;
+		return Ok(());
+	}
+
+	apply_mode(ctx, guild_id, previous, mode).await;
+
+	source.say(ctx, format!("Mode set to {}.", mode)).await?;
+
+	Ok(())
+}
+
+/// Updates the current track for a change of mode, as looping a track is
+/// handled by songbird rather than as tracks end.
+pub(crate) async fn apply_mode(
+	ctx: &Context,
+	guild_id: GuildId,
+	previous: PlaybackMode,
+	mode: PlaybackMode,
+) {
+	let manager = songbird::get(ctx)
+		.await
+		.expect("Songbird Voice Client placed in at initialisation.")
+		.clone();
+
+	if let Some(handler_lock) = manager.get(guild_id) {
+		if let Some(current) = handler_lock.lock().await.queue().current() {
+			if mode == PlaybackMode::LoopTrack {
+				let _ = current.enable_loop();
+			} else if previous == PlaybackMode::LoopTrack {
+				let _ = current.disable_loop();
+			}
+		}
+	}
+}
@@ -0,0 +1,160 @@
+use std::{borrow::Cow, time::Duration};
+
+use serenity::{
+	builder::CreateEmbed,
+	client::Context,
+	framework::standard::{macros::command, Args, CommandResult},
+	model::{channel::Message, misc::Mentionable},
+};
+use songbird::tracks::{LoopState, PlayMode, TrackHandle};
+use tokio::time::Instant;
+
+use super::helpers::CommandSource;
+use crate::utils::*;
+
+/// Time between updates of a live now playing message.
+const UPDATE_INTERVAL: Duration = Duration::from_secs(10);
+
+/// How long a live message sent for a slash command is updated, stopping
+/// before Discord stops allowing edits through the interaction after 15
+/// minutes.
+const INTERACTION_UPDATE_LIMIT: Duration = Duration::from_secs(14 * 60);
+
+/// Discord's limit on the length of an embed title.
+const MAX_TITLE_LENGTH: usize = 256;
+
+const BAR_WIDTH: usize = 20;
+
+#[command]
+#[only_in(guilds)]
+#[max_args(1)]
+#[aliases("np")]
+#[usage("[--live]")]
+/// Shows the currently playing song and how far through it is. With `--live`,
+/// the message keeps updating until the song ends, or for 14 minutes when
+/// used as a slash command.
+async fn nowplaying(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
+	match args.message().trim() {
+		"" => show_now_playing(ctx, &msg.into(), false).await,
+		"--live" => show_now_playing(ctx, &msg.into(), true).await,
+		_ => {
+			msg.channel_id
+				.say(&ctx.http, "The only option is `--live`.")
+				.await?;
+			Ok(())
+		}
+	}
+}
+
+pub(crate) async fn show_now_playing(
+	ctx: &Context,
+	source: &CommandSource<'_>,
+	live: bool,
+) -> CommandResult {
+	let guild_id = source.guild_id().unwrap();
+	let current_queue = get_current_queue(ctx, guild_id).await;
+	let (current, embed) = match (
+		current_queue.first(),
+		build_now_playing(&current_queue).await,
+	) {
+		(Some(current), Some(embed)) => (current.uuid(), embed),
+		_ => {
+			source.say(ctx, "Nothing is playing.").await?;
+			return Ok(());
+		}
+	};
+
+	let mut message = source.status(ctx, "Now playing:").await?;
+	message.edit_rich_embed(ctx, "Now playing:", embed).await?;
+
+	if !live {
+		return Ok(());
+	}
+
+	let deadline = message
+		.expires()
+		.then(|| Instant::now() + INTERACTION_UPDATE_LIMIT);
+
+	// keep updating until the song changes, the bot leaves or the message
+	// can't be edited anymore
+	loop {
+		tokio::time::sleep(UPDATE_INTERVAL).await;
+		if deadline.map_or(false, |deadline| Instant::now() >= deadline) {
+			break;
+		}
+
+		let current_queue = get_current_queue(ctx, guild_id).await;
+		if current_queue.first().map(TrackHandle::uuid) != Some(current) {
+			break;
+		}
+
+		match build_now_playing(&current_queue).await {
+			Some(embed) => {
+				message.edit_rich_embed(ctx, "Now playing:", embed).await?
+			}
+			None => break,
+		}
+	}
+
+	Ok(())
+}
+
+/// Builds an embed describing the first track of the queue, or `None` if it
+/// has already finished.
+async fn build_now_playing(queue: &[TrackHandle]) -> Option<CreateEmbed> {
+	let current = queue.first()?;
+	let state = current.get_info().await.ok()?;
+	let metadata = current.metadata();
+	let mut embed = CreateEmbed::default();
+
+	embed.title(truncate(current.get_title(), MAX_TITLE_LENGTH));
+
+	// embeds only accept web links, which rules out local files
+	if let Some(ref url) = metadata.source_url {
+		if url.starts_with("http") {
+			embed.url(url);
+		}
+	}
+	if let Some(ref thumbnail) = metadata.thumbnail {
+		embed.thumbnail(thumbnail);
+	}
+
+	let mut description = match metadata.duration {
+		Some(duration) => format!(
+			"{}\n`{} / {}`",
+			progress_bar(state.position, duration, BAR_WIDTH),
+			format_duration(state.position),
+			format_duration(duration)
+		),
+		None => format!("`{}`", format_duration(state.position)),
+	};
+	if state.playing == PlayMode::Pause {
+		description.push_str("  Paused");
+	}
+	embed.description(description);
+
+	if let Some(requester) = get_requester(current).await {
+		embed.field("Requested by", requester.user.mention(), true);
+	}
+
+	match state.loops {
+		LoopState::Infinite => {
+			embed.field("Repeats", "Forever", true);
+		}
+		LoopState::Finite(0) => {}
+		LoopState::Finite(loops) => {
+			embed.field("Repeats", loops, true);
+		}
+	}
+
+	embed.field(
+		"Up next",
+		queue
+			.get(1)
+			.map(|next| truncate(next.get_title(), MAX_TITLE_LENGTH))
+			.unwrap_or(Cow::Borrowed("Nothing")),
+		false,
+	);
+
+	Some(embed)
+}
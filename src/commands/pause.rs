@@ -4,37 +4,45 @@ use serenity::{
 	model::channel::Message,
 };
 
+use super::helpers::{require_dj, CommandSource};
+
 #[command]
 #[only_in(guilds)]
 #[num_args(0)]
 /// Pauses the currently playing song
 async fn pause(ctx: &Context, msg: &Message) -> CommandResult {
+	pause_track(ctx, &msg.into()).await
+}
+
+pub(crate) async fn pause_track(
+	ctx: &Context,
+	source: &CommandSource<'_>,
+) -> CommandResult {
+	if !require_dj(ctx, source, "pause the music").await? {
+		return Ok(());
+	}
+
 	let manager = songbird::get(ctx)
 		.await
 		.expect("Songbird Voice Client placed in at initialisation.")
 		.clone();
 
-	match manager.get(msg.guild(&ctx.cache).await.unwrap().id) {
+	match manager.get(source.guild_id().unwrap()) {
 		Some(handler_lock) => {
 			let handler = handler_lock.lock().await;
 
 			match handler.queue().current().map(|track| track.pause()) {
 				Some(_) => {
-					msg.channel_id.say(&ctx.http, "Paused!").await?;
+					source.say(ctx, "Paused!").await?;
 				}
 				None => {
-					msg.channel_id
-						.say(&ctx.http, "No tracks in queue.")
-						.await?;
+					source.say(ctx, "No tracks in queue.").await?;
 				}
 			}
 		}
 		None => {
-			msg.channel_id
-				.say(
-					&ctx.http,
-					"You must be in a voice channel to use this command.",
-				)
+			source
+				.say(ctx, "You must be in a voice channel to use this command.")
 				.await?;
 		}
 	}
@@ -4,10 +4,19 @@ use serenity::{
 	prelude::*,
 };
 
+use super::helpers::CommandSource;
+
 #[command]
 /// Causes the bot to reply with "Pong!"
 async fn ping(ctx: &Context, msg: &Message) -> CommandResult {
-	msg.channel_id.say(&ctx.http, "Pong!").await?;
+	send_pong(ctx, &msg.into()).await
+}
+
+pub(crate) async fn send_pong(
+	ctx: &Context,
+	source: &CommandSource<'_>,
+) -> CommandResult {
+	source.say(ctx, "Pong!").await?;
 
 	Ok(())
 }
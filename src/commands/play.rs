@@ -4,40 +4,87 @@ use serenity::{
 	model::channel::Message,
 };
 
-use super::helpers::join_channel;
-use crate::utils::{leave_if_empty, queue_songs, PlayParameter};
+use super::helpers::{join_channel, CommandSource};
+use crate::{
+	loads::LoadJob,
+	resolver,
+	utils::{leave_if_empty, without_playlist, PlayParameter},
+};
 
 #[command]
 #[only_in(guilds)]
 #[min_args(1)]
-/// Downloads and plays the provided link, or searches for the video on YouTube
+#[usage("[--single] link-or-search")]
+#[example("https://www.youtube.com/watch?v=dQw4w9WgXcQ")]
+#[example(
+	"--single https://www.youtube.com/watch?v=dQw4w9WgXcQ&list=RDdQw4w9WgXcQ"
+)]
+/// Downloads and plays the provided link, or searches for the video on YouTube.
+/// Links to a video inside a playlist play the whole playlist, unless
+/// `--single` is given.
 async fn play(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
-	let handler_lock = join_channel!(ctx, msg);
-	let mut result_message = msg
-		.channel_id
-		.say(&ctx.http, "Please wait, searching...")
-		.await?;
-
-	let song_stream =
-		PlayParameter::MaybeUrl(args.message().trim().to_owned()).get_tracks();
-	let mut handler = handler_lock.lock().await;
-	match queue_songs(&mut handler, song_stream).await {
+	let query = args.message().trim();
+
+	match query.strip_prefix("--single") {
+		Some(query) => play_query(ctx, &msg.into(), query.trim(), true).await,
+		None => play_query(ctx, &msg.into(), query, false).await,
+	}
+}
+
+pub(crate) async fn play_query(
+	ctx: &Context,
+	source: &CommandSource<'_>,
+	query: &str,
+	single: bool,
+) -> CommandResult {
+	let query = if single {
+		without_playlist(query)
+	} else {
+		query.into()
+	};
+
+	let handler_lock = join_channel!(ctx, source);
+	let mut result_message =
+		source.status(ctx, "Please wait, searching...").await?;
+
+	// the tracks are found first, so that the progress can show how many
+	// there are to load
+	let mut load = LoadJob::start(ctx, source, &mut result_message).await;
+	let resolver = resolver::get(ctx).await;
+	let result = match load
+		.run(
+			PlayParameter::MaybeUrl(query.into_owned())
+				.resolve(resolver.as_ref()),
+		)
+		.await
+	{
+		Ok(tracks) => {
+			let total = tracks.len();
+			let song_stream =
+				PlayParameter::Tracks(tracks).get_tracks(resolver);
+			load.queue(
+				ctx,
+				source,
+				&mut result_message,
+				&*handler_lock,
+				song_stream,
+				Some(total),
+			)
+			.await
+		}
+		Err(e) => Err(e),
+	};
+
+	match result {
 		Ok(message) => {
-			result_message
-				.edit(&ctx.http, |m| {
-					m.content("");
-					m.embed(|m| m.description(message))
-				})
-				.await?;
+			result_message.edit_embed(ctx, "", message).await?;
 		}
-		Err(message) => {
-			result_message
-				.edit(&ctx.http, |m| m.content(message))
-				.await?;
+		Err(e) => {
+			result_message.edit_text(ctx, e).await?;
 			leave_if_empty(
 				ctx,
-				handler,
-				msg.guild(&ctx.cache).await.unwrap().id,
+				handler_lock.lock().await,
+				source.guild_id().unwrap(),
 			)
 			.await;
 		}
@@ -0,0 +1,684 @@
+use serenity::{
+	client::Context,
+	framework::standard::{macros::command, Args, CommandResult},
+	model::channel::Message,
+	utils::MessageBuilder,
+};
+use tracing::error;
+
+use super::helpers::{is_dj, join_channel, CommandSource};
+use crate::{
+	error::Error,
+	library,
+	loads::LoadJob,
+	playlist::{self, Owner, Playlist, PlaylistEntry, Playlists},
+	resolver,
+	utils::{format_duration, leave_if_empty},
+};
+
+/// Number of tracks shown when showing a playlist.
+const MAX_LISTED_TRACKS: usize = 20;
+
+/// The largest file that can be imported, in bytes.
+const MAX_IMPORT_SIZE: u64 = 1024 * 1024;
+
+/// How a playlist is written out by `playlist export`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) enum ExportFormat {
+	Json,
+	/// One link on each line.
+	Links,
+}
+
+#[command]
+#[only_in(guilds)]
+#[num_args(0)]
+#[sub_commands(
+	playlist_list,
+	playlist_show,
+	playlist_save,
+	playlist_add,
+	playlist_remove,
+	playlist_delete,
+	playlist_load,
+	playlist_share,
+	playlist_unshare,
+	playlist_export,
+	playlist_import
+)]
+/// Lists your playlists and the ones shared in the server. Playlists are
+/// saved with `playlist save` and played with `playlist load`
+async fn playlist(ctx: &Context, msg: &Message) -> CommandResult {
+	list_playlists(ctx, &msg.into()).await
+}
+
+#[command("list")]
+#[only_in(guilds)]
+#[num_args(0)]
+/// Lists your playlists and the ones shared in the server
+async fn playlist_list(ctx: &Context, msg: &Message) -> CommandResult {
+	list_playlists(ctx, &msg.into()).await
+}
+
+#[command("show")]
+#[only_in(guilds)]
+#[num_args(1)]
+#[usage("name")]
+/// Shows the songs in a playlist
+async fn playlist_show(
+	ctx: &Context,
+	msg: &Message,
+	args: Args,
+) -> CommandResult {
+	show_playlist(ctx, &msg.into(), args.message().trim()).await
+}
+
+#[command("save")]
+#[only_in(guilds)]
+#[num_args(1)]
+#[usage("name")]
+#[example("chill")]
+/// Saves the queue as one of your playlists, replacing any playlist with the
+/// same name
+async fn playlist_save(
+	ctx: &Context,
+	msg: &Message,
+	args: Args,
+) -> CommandResult {
+	save_playlist(ctx, &msg.into(), args.message().trim()).await
+}
+
+#[command("add")]
+#[only_in(guilds)]
+#[min_args(1)]
+#[usage("name [links]")]
+#[example("chill https://www.youtube.com/watch?v=dQw4w9WgXcQ")]
+/// Adds links to the end of one of your playlists, or the current song if no
+/// links are given. The playlist is created if it doesn't exist
+async fn playlist_add(
+	ctx: &Context,
+	msg: &Message,
+	mut args: Args,
+) -> CommandResult {
+	let name = args.single::<String>()?;
+	let links = args.rest().split_whitespace().collect::<Vec<_>>();
+	add_to_playlist(ctx, &msg.into(), &name, &links).await
+}
+
+#[command("remove")]
+#[only_in(guilds)]
+#[num_args(2)]
+#[usage("name position")]
+#[example("chill 3")]
+/// Removes a song from one of your playlists
+async fn playlist_remove(
+	ctx: &Context,
+	msg: &Message,
+	mut args: Args,
+) -> CommandResult {
+	let name = args.single::<String>()?;
+	match args.single::<usize>() {
+		Ok(position) => {
+			remove_from_playlist(ctx, &msg.into(), &name, position).await
+		}
+		Err(_) => {
+			msg.channel_id
+				.say(&ctx.http, "Position must be a positive number.")
+				.await?;
+			Ok(())
+		}
+	}
+}
+
+#[command("delete")]
+#[only_in(guilds)]
+#[num_args(1)]
+#[usage("name")]
+/// Deletes one of your playlists
+async fn playlist_delete(
+	ctx: &Context,
+	msg: &Message,
+	args: Args,
+) -> CommandResult {
+	delete_playlist(ctx, &msg.into(), args.message().trim()).await
+}
+
+#[command("load")]
+#[only_in(guilds)]
+#[num_args(1)]
+#[usage("name")]
+#[example("chill")]
+/// Adds the songs of a playlist to the queue, looking in your playlists and
+/// then the ones shared in the server
+async fn playlist_load(
+	ctx: &Context,
+	msg: &Message,
+	args: Args,
+) -> CommandResult {
+	load_playlist(ctx, &msg.into(), args.message().trim()).await
+}
+
+#[command("share")]
+#[only_in(guilds)]
+#[num_args(1)]
+#[usage("name")]
+/// Shares a copy of one of your playlists with everyone in the server
+async fn playlist_share(
+	ctx: &Context,
+	msg: &Message,
+	args: Args,
+) -> CommandResult {
+	share_playlist(ctx, &msg.into(), args.message().trim(), true).await
+}
+
+#[command("unshare")]
+#[only_in(guilds)]
+#[num_args(1)]
+#[usage("name")]
+/// Removes a playlist shared in the server, which can be done by whoever
+/// shared it and by DJs
+async fn playlist_unshare(
+	ctx: &Context,
+	msg: &Message,
+	args: Args,
+) -> CommandResult {
+	share_playlist(ctx, &msg.into(), args.message().trim(), false).await
+}
+
+#[command("export")]
+#[only_in(guilds)]
+#[min_args(1)]
+#[max_args(2)]
+#[usage("name [json|links]")]
+#[example("chill links")]
+/// Sends a playlist as a file, either JSON or a list of links
+async fn playlist_export(
+	ctx: &Context,
+	msg: &Message,
+	mut args: Args,
+) -> CommandResult {
+	let name = args.single::<String>()?;
+	let format = match args.single::<String>().as_deref() {
+		Ok("links") | Ok("urls") => ExportFormat::Links,
+		Ok("json") | Err(_) => ExportFormat::Json,
+		Ok(_) => {
+			msg.channel_id
+				.say(&ctx.http, "Format must be `json` or `links`.")
+				.await?;
+			return Ok(());
+		}
+	};
+
+	export_playlist(ctx, &msg.into(), &name, format).await
+}
+
+#[command("import")]
+#[only_in(guilds)]
+#[min_args(1)]
+#[usage("name [links]")]
+#[example("chill")]
+/// Creates one of your playlists from an attached file, as sent by
+/// `playlist export`, or from links. Replaces any playlist with the same
+/// name
+async fn playlist_import(
+	ctx: &Context,
+	msg: &Message,
+	mut args: Args,
+) -> CommandResult {
+	let name = args.single::<String>()?;
+	if !args.rest().trim().is_empty() {
+		return import_playlist(ctx, &msg.into(), &name, args.rest()).await;
+	}
+
+	let attachment = match msg.attachments.first() {
+		Some(attachment) if attachment.size <= MAX_IMPORT_SIZE => attachment,
+		Some(_) => {
+			msg.channel_id
+				.say(&ctx.http, "The file is too large to import.")
+				.await?;
+			return Ok(());
+		}
+		None => {
+			msg.channel_id
+				.say(&ctx.http, "Attach a playlist file or give some links.")
+				.await?;
+			return Ok(());
+		}
+	};
+
+	match attachment.download().await {
+		Ok(contents) => {
+			let contents = String::from_utf8_lossy(&contents);
+			import_playlist(ctx, &msg.into(), &name, &contents).await
+		}
+		Err(e) => {
+			error!("Could not download {}: {:?}", attachment.url, e);
+			msg.channel_id
+				.say(&ctx.http, "Could not download the file.")
+				.await?;
+			Ok(())
+		}
+	}
+}
+
+pub(crate) async fn list_playlists(
+	ctx: &Context,
+	source: &CommandSource<'_>,
+) -> CommandResult {
+	let user = Owner::User(source.author().id);
+	let guild = Owner::Guild(source.guild_id().unwrap());
+	let (own, shared) =
+		match (Playlists::load(user).await, Playlists::load(guild).await) {
+			(Ok(own), Ok(shared)) => (own, shared),
+			(Err(e), _) | (_, Err(e)) => {
+				return report(ctx, source, Err(e.into())).await;
+			}
+		};
+
+	if own.playlists.is_empty() && shared.playlists.is_empty() {
+		source
+			.say(
+				ctx,
+				"There are no playlists yet, use `playlist save` to save the \
+				 queue as one.",
+			)
+			.await?;
+		return Ok(());
+	}
+
+	let mut message = MessageBuilder::new();
+	if !own.playlists.is_empty() {
+		message.push_bold_line("Your playlists");
+		for (name, playlist) in &own.playlists {
+			message
+				.push_mono(name)
+				.push_line(format!(" | {} song(s)", playlist.tracks.len()));
+		}
+	}
+	if !shared.playlists.is_empty() {
+		message.push_bold_line("Shared in this server");
+		for (name, playlist) in &shared.playlists {
+			message
+				.push_mono(name)
+				.push(format!(" | {} song(s)", playlist.tracks.len()));
+			if let Some(user) = playlist.shared_by {
+				message.push(" | shared by ").mention(&user);
+			}
+			message.push_line("");
+		}
+	}
+
+	source.say_embed(ctx, message).await?;
+
+	Ok(())
+}
+
+pub(crate) async fn show_playlist(
+	ctx: &Context,
+	source: &CommandSource<'_>,
+	name: &str,
+) -> CommandResult {
+	let playlist = match find(source, name).await {
+		Ok(playlist) => playlist,
+		Err(e) => return report(ctx, source, Err(e)).await,
+	};
+
+	let mut message = MessageBuilder::new();
+	for (index, track) in
+		playlist.tracks.iter().enumerate().take(MAX_LISTED_TRACKS)
+	{
+		message
+			.push_mono(index + 1)
+			.push(" | ")
+			.push_safe(track.title.as_deref().unwrap_or(&track.url))
+			.push("  ")
+			.push_mono_line(
+				track
+					.duration
+					.map(format_duration)
+					.as_deref()
+					.unwrap_or("No info"),
+			);
+	}
+	if playlist.tracks.len() > MAX_LISTED_TRACKS {
+		message.push_line(format!(
+			"and {} more",
+			playlist.tracks.len() - MAX_LISTED_TRACKS
+		));
+	}
+
+	source.say_embed(ctx, message).await?;
+
+	Ok(())
+}
+
+pub(crate) async fn save_playlist(
+	ctx: &Context,
+	source: &CommandSource<'_>,
+	name: &str,
+) -> CommandResult {
+	let manager = songbird::get(ctx)
+		.await
+		.expect("Songbird Voice Client placed in at initialisation.")
+		.clone();
+
+	let queue = match manager.get(source.guild_id().unwrap()) {
+		Some(handler_lock) => handler_lock.lock().await.queue().current_queue(),
+		None => Vec::new(),
+	};
+	let playlist = Playlist::capture(&queue);
+	if playlist.tracks.is_empty() {
+		source
+			.say(ctx, "There is nothing in the queue to save.")
+			.await?;
+		return Ok(());
+	}
+
+	let count = playlist.tracks.len();
+	let result = Playlists::update(Owner::User(source.author().id), |own| {
+		own.insert(name, playlist)
+	})
+	.await
+	.map(|replaced| {
+		let verb = if replaced { "Replaced" } else { "Saved" };
+		format!("{} `{}` with {} song(s).", verb, name, count)
+	});
+
+	report(ctx, source, result).await
+}
+
+pub(crate) async fn add_to_playlist(
+	ctx: &Context,
+	source: &CommandSource<'_>,
+	name: &str,
+	links: &[&str],
+) -> CommandResult {
+	let tracks = if links.is_empty() {
+		let manager = songbird::get(ctx)
+			.await
+			.expect("Songbird Voice Client placed in at initialisation.")
+			.clone();
+
+		let current = match manager.get(source.guild_id().unwrap()) {
+			Some(handler_lock) => handler_lock.lock().await.queue().current(),
+			None => None,
+		};
+		match current.as_ref().and_then(PlaylistEntry::from_handle) {
+			Some(track) => Ok(vec![track]),
+			None => {
+				source.say(ctx, "Nothing is playing.").await?;
+				return Ok(());
+			}
+		}
+	} else {
+		links
+			.iter()
+			.map(|link| PlaylistEntry::from_link(link))
+			.collect::<Result<Vec<_>, _>>()
+	};
+
+	let result = match tracks {
+		Ok(tracks) => {
+			let added = tracks.len();
+			Playlists::update(Owner::User(source.author().id), |own| {
+				own.append(name, tracks)
+			})
+			.await
+			.map(|count| {
+				format!(
+					"Added {} song(s) to `{}`, which now has {}.",
+					added, name, count
+				)
+			})
+		}
+		Err(e) => Err(e),
+	};
+
+	report(ctx, source, result).await
+}
+
+pub(crate) async fn remove_from_playlist(
+	ctx: &Context,
+	source: &CommandSource<'_>,
+	name: &str,
+	position: usize,
+) -> CommandResult {
+	let result = Playlists::update(Owner::User(source.author().id), |own| {
+		let playlist = own.get_mut(name)?;
+		if position == 0 || position > playlist.tracks.len() {
+			return Err(Error::NoPlaylistPosition(position));
+		}
+
+		Ok(playlist.tracks.remove(position - 1))
+	})
+	.await
+	.map(|track| {
+		MessageBuilder::new()
+			.push("Removed ")
+			.push_safe(track.title.unwrap_or(track.url))
+			.push(" from ")
+			.push_mono_safe(name)
+			.push(".")
+			.build()
+	});
+
+	report(ctx, source, result).await
+}
+
+pub(crate) async fn delete_playlist(
+	ctx: &Context,
+	source: &CommandSource<'_>,
+	name: &str,
+) -> CommandResult {
+	let result = Playlists::update(Owner::User(source.author().id), |own| {
+		own.remove(name)
+	})
+	.await
+	.map(|_| format!("Deleted `{}`.", name));
+
+	report(ctx, source, result).await
+}
+
+pub(crate) async fn load_playlist(
+	ctx: &Context,
+	source: &CommandSource<'_>,
+	name: &str,
+) -> CommandResult {
+	let playlist = match find(source, name).await {
+		Ok(playlist) => playlist,
+		Err(e) => return report(ctx, source, Err(e)).await,
+	};
+
+	let handler_lock = join_channel!(ctx, source);
+	let mut result_message = source
+		.status(
+			ctx,
+			format!(
+				"Please wait, loading {} song(s) from `{}`...",
+				playlist.tracks.len(),
+				name
+			),
+		)
+		.await?;
+
+	let mut load = LoadJob::start(ctx, source, &mut result_message).await;
+	let total = playlist.tracks.len();
+	let song_stream =
+		playlist.get_tracks(resolver::get(ctx).await, library::get(ctx).await);
+	match load
+		.queue(
+			ctx,
+			source,
+			&mut result_message,
+			&*handler_lock,
+			song_stream,
+			Some(total),
+		)
+		.await
+	{
+		Ok(message) => {
+			result_message.edit_embed(ctx, "", message).await?;
+		}
+		Err(e) => {
+			result_message.edit_text(ctx, e).await?;
+			leave_if_empty(
+				ctx,
+				handler_lock.lock().await,
+				source.guild_id().unwrap(),
+			)
+			.await;
+		}
+	}
+
+	Ok(())
+}
+
+/// Shares a copy of a user's playlist in the guild, or removes one that was
+/// shared.
+pub(crate) async fn share_playlist(
+	ctx: &Context,
+	source: &CommandSource<'_>,
+	name: &str,
+	share: bool,
+) -> CommandResult {
+	let guild_id = source.guild_id().unwrap();
+	let author = source.author().id;
+	let dj = is_dj(ctx, guild_id, author).await;
+	let may_change = |playlist: &Playlist| {
+		dj || playlist.shared_by.map_or(true, |user| user == author)
+	};
+
+	let result = if share {
+		let playlist = match Playlists::load(Owner::User(author)).await {
+			Ok(mut own) => own.remove(name),
+			Err(e) => Err(e.into()),
+		};
+
+		match playlist {
+			Ok(playlist) => {
+				Playlists::update(Owner::Guild(guild_id), |shared| {
+					if let Ok(existing) = shared.get(name) {
+						if !may_change(existing) {
+							return Err(Error::PlaylistSharedByOther(
+								name.to_string(),
+							));
+						}
+					}
+
+					shared.insert(
+						name,
+						Playlist {
+							shared_by: Some(author),
+							..playlist
+						},
+					)
+				})
+				.await
+				.map(|_| {
+					format!(
+						"Shared `{}`, anyone in the server can now load it.",
+						name
+					)
+				})
+			}
+			Err(e) => Err(e),
+		}
+	} else {
+		Playlists::update(Owner::Guild(guild_id), |shared| {
+			if !may_change(shared.get(name)?) {
+				return Err(Error::PlaylistSharedByOther(name.to_string()));
+			}
+
+			shared.remove(name)
+		})
+		.await
+		.map(|_| format!("`{}` is no longer shared.", name))
+	};
+
+	report(ctx, source, result).await
+}
+
+pub(crate) async fn export_playlist(
+	ctx: &Context,
+	source: &CommandSource<'_>,
+	name: &str,
+	format: ExportFormat,
+) -> CommandResult {
+	let playlist = match find(source, name).await {
+		Ok(playlist) => playlist,
+		Err(e) => return report(ctx, source, Err(e)).await,
+	};
+
+	let name = name.to_lowercase();
+	let (contents, filename) = match format {
+		ExportFormat::Json => {
+			(playlist.export_json(&name), format!("{}.json", name))
+		}
+		ExportFormat::Links => {
+			(playlist.export_links(), format!("{}.txt", name))
+		}
+	};
+
+	source
+		.send_file(
+			ctx,
+			format!(
+				"Exported `{}`, use `playlist import` to load it back.",
+				name
+			),
+			&filename,
+			contents.as_bytes(),
+		)
+		.await?;
+
+	Ok(())
+}
+
+pub(crate) async fn import_playlist(
+	ctx: &Context,
+	source: &CommandSource<'_>,
+	name: &str,
+	contents: &str,
+) -> CommandResult {
+	let result = match Playlist::import(contents) {
+		Ok(playlist) => {
+			let count = playlist.tracks.len();
+			Playlists::update(Owner::User(source.author().id), |own| {
+				own.insert(name, playlist)
+			})
+			.await
+			.map(|_| format!("Imported {} song(s) into `{}`.", count, name))
+		}
+		Err(e) => Err(e),
+	};
+
+	report(ctx, source, result).await
+}
+
+async fn find(
+	source: &CommandSource<'_>,
+	name: &str,
+) -> Result<Playlist, Error> {
+	playlist::find(source.author().id, source.guild_id().unwrap(), name).await
+}
+
+/// Replies with the outcome of a change to playlists, logging errors that
+/// aren't the user's fault.
+async fn report(
+	ctx: &Context,
+	source: &CommandSource<'_>,
+	result: Result<String, Error>,
+) -> CommandResult {
+	match result {
+		Ok(message) => source.say(ctx, message).await?,
+		Err(Error::Io(e)) => {
+			error!("Could not access playlists: {}", e);
+			source
+				.say(ctx, "Could not read or save the playlists.")
+				.await?;
+		}
+		Err(e) => source.say(ctx, e).await?,
+	}
+
+	Ok(())
+}
@@ -1,12 +1,23 @@
+use std::collections::VecDeque;
+
 use futures_util::StreamExt;
 use serenity::{
+	async_trait,
 	client::Context,
 	framework::standard::{macros::command, Args, CommandResult},
 	model::channel::Message,
+	prelude::Mutex,
 };
+use songbird::{tracks::Track, Call};
 
-use super::helpers::join_channel;
-use crate::utils::{leave_if_empty, queue_songs, PlayParameter};
+use super::helpers::{join_channel, CommandSource};
+use crate::{
+	loads::LoadJob,
+	resolver,
+	utils::{
+		leave_if_empty, prefetch, without_playlist, EnqueueTrack, PlayParameter,
+	},
+};
 
 #[command]
 #[only_in(guilds)]
@@ -14,41 +25,44 @@ use crate::utils::{leave_if_empty, queue_songs, PlayParameter};
 /// Downloads and plays the provided link, or searches for the video on YouTube.
 /// Plays after the current song.
 async fn playnext(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
-	let handler_lock = join_channel!(ctx, msg);
-	let mut result_message = msg
-		.channel_id
-		.say(&ctx.http, "Please wait, searching...")
-		.await?;
-
-	let song_stream = PlayParameter::MaybeUrl(args.message().trim().to_owned())
-		.get_tracks()
+	play_next(ctx, &msg.into(), args.message().trim()).await
+}
+
+pub(crate) async fn play_next(
+	ctx: &Context,
+	source: &CommandSource<'_>,
+	query: &str,
+) -> CommandResult {
+	let handler_lock = join_channel!(ctx, source);
+	let mut result_message =
+		source.status(ctx, "Please wait, searching...").await?;
+
+	// only one track is taken, which should be the linked video rather than
+	// the start of its playlist
+	let mut load = LoadJob::start(ctx, source, &mut result_message).await;
+	let song_stream = PlayParameter::MaybeUrl(without_playlist(query).into())
+		.get_tracks(resolver::get(ctx).await)
 		.take(1);
-	let mut handler = handler_lock.lock().await;
-	match queue_songs(&mut handler, song_stream).await {
+	match load
+		.queue(
+			ctx,
+			source,
+			&mut result_message,
+			&NextInQueue(&handler_lock),
+			song_stream,
+			Some(1),
+		)
+		.await
+	{
 		Ok(message) => {
-			handler.queue().modify_queue(|queue| {
-				if queue.len() > 1 {
-					if let Some(track) = queue.pop_back() {
-						queue.push_front(track);
-						queue.swap(0, 1);
-					}
-				}
-			});
-			result_message
-				.edit(&ctx.http, |m| {
-					m.content("");
-					m.embed(|m| m.description(message))
-				})
-				.await?;
+			result_message.edit_embed(ctx, "", message).await?;
 		}
-		Err(message) => {
-			result_message
-				.edit(&ctx.http, |m| m.content(message))
-				.await?;
+		Err(e) => {
+			result_message.edit_text(ctx, e).await?;
 			leave_if_empty(
 				ctx,
-				handler,
-				msg.guild(&ctx.cache).await.unwrap().id,
+				handler_lock.lock().await,
+				source.guild_id().unwrap(),
 			)
 			.await;
 		}
@@ -56,3 +70,60 @@ async fn playnext(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
 
 	Ok(())
 }
+
+/// Puts each queued track right after the one that is playing.
+struct NextInQueue<'a>(&'a Mutex<Call>);
+
+#[async_trait]
+impl EnqueueTrack for NextInQueue<'_> {
+	async fn enqueue_track(&self, track: Track) {
+		let uuid = track.handle.uuid();
+		self.0.enqueue_track(track).await;
+
+		// other tracks may have been queued since, so the track is found by
+		// its id rather than taken from the back
+		let handler = self.0.lock().await;
+		handler.queue().modify_queue(|queue| {
+			move_to_next(queue, |track| track.handle().uuid() == uuid);
+		});
+		prefetch(handler.queue());
+	}
+
+	async fn queue_len(&self) -> usize {
+		self.0.queue_len().await
+	}
+}
+
+/// Moves the first queued track matching `is_track` after the playing one.
+fn move_to_next<T>(queue: &mut VecDeque<T>, is_track: impl Fn(&T) -> bool) {
+	if let Some(index) = queue.iter().skip(1).position(is_track) {
+		if let Some(track) = queue.remove(index + 1) {
+			queue.insert(1, track);
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::collections::VecDeque;
+
+	use super::move_to_next;
+
+	#[test]
+	fn test_move_to_next() {
+		let mut queue = VecDeque::from(vec![1, 2, 3, 4]);
+		move_to_next(&mut queue, |&track| track == 4);
+		assert_eq!(queue, [1, 4, 2, 3]);
+
+		// the playing track is never moved
+		move_to_next(&mut queue, |&track| track == 1);
+		assert_eq!(queue, [1, 4, 2, 3]);
+
+		move_to_next(&mut queue, |&track| track == 5);
+		assert_eq!(queue, [1, 4, 2, 3]);
+
+		let mut queue = VecDeque::from(vec![1]);
+		move_to_next(&mut queue, |&track| track == 1);
+		assert_eq!(queue, [1]);
+	}
+}
@@ -1,63 +1,175 @@
+use std::{convert::TryFrom, time::Duration};
+
+use once_cell::sync::Lazy;
 use serenity::{
 	client::Context,
-	framework::standard::{macros::command, CommandResult},
-	model::channel::Message,
+	framework::standard::{macros::command, Args, CommandResult},
+	model::channel::{Message, ReactionType},
 	utils::{EmbedMessageBuilding, MessageBuilder},
 };
 use songbird::tracks::TrackHandle;
 
-use crate::utils::*;
+use super::helpers::CommandSource;
+use crate::{
+	config,
+	settings::{self, PlaybackMode},
+	utils::*,
+};
+
+/// The most characters of a title shown in the queue.
+const MAX_TITLE_LENGTH: usize = 100;
+
+/// Discord's limit on the length of an embed description.
+const MAX_DESCRIPTION_LENGTH: usize = 4096;
+
+static PAGE_REACTS: Lazy<[ReactionType; 2]> = Lazy::new(|| {
+	[
+		ReactionType::try_from("◀️").unwrap(),
+		ReactionType::try_from("▶️").unwrap(),
+	]
+});
 
 #[command]
 #[only_in(guilds)]
-#[num_args(0)]
-async fn queue(ctx: &Context, msg: &Message) -> CommandResult {
-	let guild = msg.guild(&ctx.cache).await.unwrap();
-	let manager = songbird::get(ctx)
-		.await
-		.expect("Songbird Voice Client placed in at initialisation.")
-		.clone();
-
-	match manager.get(guild.id) {
-		Some(handler_lock) => {
-			let handler = handler_lock.lock().await;
+#[max_args(1)]
+#[usage("[page]")]
+/// Shows the current queue
+async fn queue(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
+	if args.is_empty() {
+		return show_queue(ctx, &msg.into(), 1).await;
+	}
 
+	match args.single::<usize>() {
+		Ok(page) => show_queue(ctx, &msg.into(), page).await,
+		Err(_) => {
 			msg.channel_id
-				.send_message(&ctx.http, |m| {
-					m.embed(|e| {
-						e.description(build_queue_message(
-							&handler.queue().current_queue(),
-						))
-					})
-				})
+				.say(&ctx.http, "Page must be a positive number.")
 				.await?;
+			Ok(())
+		}
+	}
+}
+
+pub(crate) async fn show_queue(
+	ctx: &Context,
+	source: &CommandSource<'_>,
+	page: usize,
+) -> CommandResult {
+	let guild_id = source.guild_id().unwrap();
+	let current_queue = get_current_queue(ctx, guild_id).await;
+	if current_queue.is_empty() {
+		source.say(ctx, "Queue is empty.").await?;
+		return Ok(());
+	}
+
+	let mode = settings::get(&ctx.data, guild_id).await.mode;
+	let mut page = page.clamp(1, page_count(current_queue.len()));
+	let mut queue_message = source.status(ctx, "Current queue:").await?;
+	queue_message
+		.edit_embed(
+			ctx,
+			"Current queue:",
+			build_queue_message(&current_queue, page, mode).await,
+		)
+		.await?;
+
+	if page_count(current_queue.len()) == 1 {
+		return Ok(());
+	}
+
+	for emoji in PAGE_REACTS.iter().cloned() {
+		queue_message.message().react(&ctx.http, emoji).await?;
+	}
+
+	// turn pages until nobody has reacted for a minute
+	while let Some(reaction) = queue_message
+		.message()
+		.await_reaction(&ctx)
+		.timeout(Duration::from_secs(60))
+		.author_id(source.author().id)
+		.filter(|reaction| PAGE_REACTS.contains(&reaction.as_ref().emoji))
+		.await
+	{
+		let reaction = reaction.as_inner_ref();
+
+		// the queue may have changed since the last page was shown
+		let current_queue = get_current_queue(ctx, guild_id).await;
+		if current_queue.is_empty() {
+			break;
 		}
-		None => {
-			msg.channel_id.say(&ctx.http, "Queue is empty.").await?;
+
+		page = if reaction.emoji == PAGE_REACTS[0] {
+			page.saturating_sub(1)
+		} else {
+			page + 1
 		}
+		.clamp(1, page_count(current_queue.len()));
+
+		// removing the reaction lets it be used again, but needs the Manage
+		// Messages permission, so failing is fine
+		let _ = reaction.delete(&ctx.http).await;
+		queue_message
+			.edit_embed(
+				ctx,
+				"Current queue:",
+				build_queue_message(&current_queue, page, mode).await,
+			)
+			.await?;
 	}
 
+	let _ = queue_message.message().delete_reactions(&ctx.http).await;
+
 	Ok(())
 }
 
-pub(crate) fn build_queue_message(queue: &[TrackHandle]) -> MessageBuilder {
+fn page_count(track_count: usize) -> usize {
+	let page_size = config::get().queue_page_size;
+	((track_count + page_size - 1) / page_size).max(1)
+}
+
+/// Builds one page of the queue, followed by the number of tracks, how long
+/// they will take to play and the playback mode. The page is cut short if its
+/// lines would be too long for an embed.
+pub(crate) async fn build_queue_message(
+	queue: &[TrackHandle],
+	page: usize,
+	mode: PlaybackMode,
+) -> MessageBuilder {
+	let (remaining, complete) = remaining_duration(queue).await;
+	let summary = format!(
+		"\n{} track(s), {}{} remaining | Mode: {} | Page {} of {}",
+		queue.len(),
+		format_duration(remaining),
+		if complete { "" } else { "+" },
+		mode,
+		page,
+		page_count(queue.len()),
+	);
+	// room is kept for the summary and a line marking that the page was cut
+	let mut budget = MAX_DESCRIPTION_LENGTH - summary.chars().count() - 2;
+
 	let mut queue_message = MessageBuilder::new();
-	queue.iter().enumerate().for_each(|(index, metadata)| {
+	let page_size = config::get().queue_page_size;
+	let start = (page - 1) * page_size;
+	for (index, metadata) in
+		queue.iter().enumerate().skip(start).take(page_size)
+	{
+		let mut line = MessageBuilder::new();
 		if index == 0 {
-			queue_message.push_mono("Now Playing");
+			line.push_mono("Now Playing");
 		} else {
-			queue_message.push_mono(index);
+			line.push_mono(index);
 		}
-		queue_message.push(" | ");
+		line.push(" | ");
 
+		let title = truncate(metadata.get_title(), MAX_TITLE_LENGTH);
 		match metadata.metadata().source_url {
-			Some(ref url) => queue_message
-				.push_named_link(escape_markdown(metadata.get_title()), url),
-			None => queue_message.push_mono_safe(metadata.get_title()),
+			Some(ref url) => line.push_named_link(escape_markdown(&title), url),
+			None => line.push_mono_safe(title),
 		};
 
-		queue_message.push("  ");
-		queue_message.push_mono_line(
+		line.push("  ");
+		line.push_mono(
 			metadata
 				.metadata()
 				.duration
@@ -65,7 +177,121 @@ pub(crate) fn build_queue_message(queue: &[TrackHandle]) -> MessageBuilder {
 				.as_deref()
 				.unwrap_or("No info"),
 		);
-	});
 
+		if let Some(requester) = get_requester(metadata).await {
+			line.push(" ").mention(&requester.user);
+		}
+		line.push("\n");
+
+		let line = line.build();
+		let length = line.chars().count();
+		if length > budget {
+			queue_message.push_line("…");
+			break;
+		}
+		budget -= length;
+		queue_message.push(line);
+	}
+
+	queue_message.push(summary);
 	queue_message
 }
+
+/// Adds up how long the queue has left to play, also returning whether the
+/// duration of every track is known.
+async fn remaining_duration(queue: &[TrackHandle]) -> (Duration, bool) {
+	let mut complete = true;
+	let mut remaining = queue
+		.iter()
+		.filter_map(|track| {
+			complete &= track.metadata().duration.is_some();
+			track.metadata().duration
+		})
+		.sum::<Duration>();
+
+	if let Some(current) = queue.first() {
+		if let Ok(state) = current.get_info().await {
+			remaining = remaining.saturating_sub(state.position);
+		}
+	}
+
+	(remaining, complete)
+}
+
+#[cfg(test)]
+mod tests {
+	use std::time::Duration;
+
+	use songbird::tracks::{create_player, TrackHandle};
+
+	use super::{build_queue_message, page_count, MAX_DESCRIPTION_LENGTH};
+	use crate::{
+		resolver::{FakeResolver, Resolver, TrackDescriptor},
+		settings::PlaybackMode,
+	};
+
+	/// Creates tracks with the given title and URL lengths. Only the handles
+	/// are kept, so that asking for their state fails instead of waiting for
+	/// a driver.
+	async fn tracks(
+		count: usize,
+		title: usize,
+		url: usize,
+	) -> Vec<TrackHandle> {
+		let mut handles = Vec::new();
+		for index in 0..count {
+			let track = TrackDescriptor {
+				title: Some("a".repeat(title)),
+				duration: Some(Duration::from_secs(60)),
+				..TrackDescriptor::from_url(format!(
+					"https://example.com/{}/{}",
+					index,
+					"b".repeat(url)
+				))
+			};
+			let input = FakeResolver::default().input(&track).await.unwrap();
+			handles.push(create_player(input).1);
+		}
+		handles
+	}
+
+	#[test]
+	fn test_page_count() {
+		assert_eq!(page_count(0), 1);
+		assert_eq!(page_count(10), 1);
+		assert_eq!(page_count(11), 2);
+	}
+
+	#[tokio::test]
+	async fn test_build_queue_message() {
+		let queue = tracks(12, 10, 10).await;
+		let message = build_queue_message(&queue, 2, PlaybackMode::LoopQueue)
+			.await
+			.build();
+
+		assert!(message.starts_with("`10` | "));
+		assert!(message.contains("`11` | "));
+		assert!(message.ends_with(
+			"12 track(s), 12:00 remaining | Mode: Loop queue | Page 2 of 2"
+		));
+	}
+
+	#[tokio::test]
+	async fn test_build_queue_message_long_titles() {
+		let queue = tracks(10, 1000, 10).await;
+		let message = build_queue_message(&queue, 1, PlaybackMode::Normal)
+			.await
+			.build();
+		assert!(!message.contains(&"a".repeat(101)));
+		assert!(message.contains(&format!("{}…", "a".repeat(99))));
+
+		// the links can't be shortened, so lines are left out instead
+		let queue = tracks(10, 100, 1000).await;
+		let message = build_queue_message(&queue, 1, PlaybackMode::Normal)
+			.await
+			.build();
+		assert!(message.chars().count() <= MAX_DESCRIPTION_LENGTH);
+		assert!(message.contains("…\n"));
+		assert!(message.contains("Page 1 of 1"));
+	}
+}
@@ -0,0 +1,201 @@
+use std::{collections::HashSet, ops::RangeInclusive};
+
+use serenity::{
+	client::Context,
+	framework::standard::{macros::command, Args, CommandResult},
+	model::{channel::Message, id::UserId},
+	utils::{parse_username, MessageBuilder},
+};
+
+use super::helpers::{is_dj, CommandSource};
+use crate::utils::*;
+
+/// Number of removed tracks listed by name.
+const MAX_LISTED_TRACKS: usize = 10;
+
+/// Which tracks to remove from the queue.
+#[derive(Debug, PartialEq)]
+pub(crate) enum Selection {
+	Positions(Vec<RangeInclusive<usize>>),
+	User(UserId),
+	/// Tracks with a title containing the text, ignoring case.
+	Match(String),
+}
+
+#[command]
+#[only_in(guilds)]
+#[min_args(1)]
+#[usage("positions | @user | --match text")]
+#[example("5-20")]
+#[example("3 5 7")]
+#[example("@user")]
+#[example("--match remix")]
+/// Removes tracks from the queue by position or range, by the user that
+/// queued them, or by part of their title. The current song is never removed,
+/// use `skip` for that.
+async fn remove(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
+	match parse_selection(args.message()) {
+		Some(selection) => remove_tracks(ctx, &msg.into(), selection).await,
+		None => {
+			msg.channel_id
+				.say(
+					&ctx.http,
+					"Give positions such as `3 5-7`, a user mention, or \
+					 `--match` followed by part of a title.",
+				)
+				.await?;
+			Ok(())
+		}
+	}
+}
+
+/// Reads the tracks chosen for the prefix command.
+fn parse_selection(input: &str) -> Option<Selection> {
+	let input = input.trim();
+
+	if let Some(text) = input.strip_prefix("--match") {
+		let text = text.trim();
+		(!text.is_empty()).then(|| Selection::Match(text.to_string()))
+	} else if let Some(user) = parse_username(input) {
+		Some(Selection::User(UserId(user)))
+	} else {
+		parse_positions(input).map(Selection::Positions)
+	}
+}
+
+pub(crate) async fn remove_tracks(
+	ctx: &Context,
+	source: &CommandSource<'_>,
+	selection: Selection,
+) -> CommandResult {
+	let manager = songbird::get(ctx)
+		.await
+		.expect("Songbird Voice Client placed in at initialisation.")
+		.clone();
+
+	let handler_lock = match manager.get(source.guild_id().unwrap()) {
+		Some(handler_lock) => handler_lock,
+		None => {
+			source.say(ctx, "Not playing in voice channel").await?;
+			return Ok(());
+		}
+	};
+
+	// everyone else can only remove the songs they queued themselves
+	let author = source.author().id;
+	let dj = is_dj(ctx, source.guild_id().unwrap(), author).await;
+	let mut kept = 0;
+
+	let handler = handler_lock.lock().await;
+	let queue = handler.queue();
+
+	// the requester can only be read asynchronously, so the tracks are chosen
+	// before modifying the queue, which then only has to compare IDs
+	let mut selected = HashSet::new();
+	for (index, track) in queue.current_queue().iter().enumerate().skip(1) {
+		let is_selected = match selection {
+			Selection::Positions(ref ranges) => {
+				ranges.iter().any(|range| range.contains(&index))
+			}
+			Selection::User(user) => get_requester(track)
+				.await
+				.map(|requester| requester.user == user)
+				.unwrap_or(false),
+			Selection::Match(ref text) => track
+				.get_title()
+				.to_lowercase()
+				.contains(&text.to_lowercase()),
+		};
+
+		if !is_selected {
+			continue;
+		}
+
+		let own = get_requester(track)
+			.await
+			.map_or(false, |requester| requester.user == author);
+		if dj || own {
+			selected.insert(track.uuid());
+		} else {
+			kept += 1;
+		}
+	}
+
+	let removed = queue.modify_queue(|queue| {
+		let mut removed = Vec::new();
+		let mut index = 1;
+		while index < queue.len() {
+			if selected.contains(&queue[index].uuid()) {
+				removed.extend(queue.remove(index));
+			} else {
+				index += 1;
+			}
+		}
+		removed
+	});
+	prefetch(queue);
+	drop(handler);
+
+	if removed.is_empty() {
+		let message = if kept > 0 {
+			"Only members with the DJ role can remove songs queued by others."
+		} else {
+			"No tracks matched, nothing was removed."
+		};
+		source.say(ctx, message).await?;
+		return Ok(());
+	}
+
+	let mut message = MessageBuilder::new();
+	message.push_line(format!("Removed {} track(s):", removed.len()));
+	for track in removed.iter().take(MAX_LISTED_TRACKS) {
+		message.push("- ").push_line_safe(track.get_title());
+	}
+	if removed.len() > MAX_LISTED_TRACKS {
+		message.push_line(format!(
+			"and {} more",
+			removed.len() - MAX_LISTED_TRACKS
+		));
+	}
+	if kept > 0 {
+		message.push_line(format!(
+			"{} track(s) queued by others were kept, only members with the \
+			 DJ role can remove them.",
+			kept
+		));
+	}
+
+	for track in removed {
+		mark_dequeued(&track).await;
+		let _ = track.stop();
+	}
+
+	source.say_embed(ctx, message).await?;
+
+	Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+	use serenity::model::id::UserId;
+
+	use super::{parse_selection, Selection};
+
+	#[test]
+	fn test_parse_selection() {
+		assert_eq!(
+			parse_selection(" 3 5-7 "),
+			Some(Selection::Positions(vec![3..=3, 5..=7]))
+		);
+		assert_eq!(
+			parse_selection("<@!123>"),
+			Some(Selection::User(UserId(123)))
+		);
+		assert_eq!(
+			parse_selection("--match  Live Remix "),
+			Some(Selection::Match("Live Remix".to_string()))
+		);
+		assert_eq!(parse_selection("--match"), None);
+		assert_eq!(parse_selection("someone"), None);
+	}
+}
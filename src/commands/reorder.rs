@@ -0,0 +1,183 @@
+use std::collections::VecDeque;
+
+use serenity::{
+	client::Context,
+	framework::standard::{macros::command, Args, CommandResult},
+	model::channel::Message,
+	utils::MessageBuilder,
+};
+
+use super::helpers::{require_dj, CommandSource};
+use crate::utils::{prefetch, ObtainTitle};
+
+/// A change to the order of the queue, by positions in the queue.
+pub(crate) enum Reorder {
+	/// Takes out the track at the first position, inserting it at the second.
+	Move(usize, usize),
+	Swap(usize, usize),
+}
+
+impl Reorder {
+	/// Changes the order of the queue, or gives `None` if either position is
+	/// past its end.
+	fn apply<T>(&self, queue: &mut VecDeque<T>) -> Option<()> {
+		match *self {
+			Self::Move(first, second) | Self::Swap(first, second)
+				if first >= queue.len() || second >= queue.len() =>
+			{
+				None
+			}
+			Self::Move(first, second) => {
+				let track = queue.remove(first)?;
+				queue.insert(second, track);
+				Some(())
+			}
+			Self::Swap(first, second) => {
+				queue.swap(first, second);
+				Some(())
+			}
+		}
+	}
+}
+
+#[command("move")]
+#[only_in(guilds)]
+#[num_args(2)]
+#[usage("from to")]
+#[example("5 1")]
+/// Moves the song at one position in the queue to another
+async fn move_track(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
+	parse_and_reorder(ctx, msg, args, Reorder::Move).await
+}
+
+#[command]
+#[only_in(guilds)]
+#[num_args(2)]
+#[usage("position position")]
+#[example("2 4")]
+/// Swaps the songs at two positions in the queue
+async fn swap(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
+	parse_and_reorder(ctx, msg, args, Reorder::Swap).await
+}
+
+async fn parse_and_reorder(
+	ctx: &Context,
+	msg: &Message,
+	mut args: Args,
+	reorder: fn(usize, usize) -> Reorder,
+) -> CommandResult {
+	let parsed = args
+		.single::<usize>()
+		.and_then(|first| args.single::<usize>().map(|second| (first, second)));
+
+	match parsed {
+		Ok((first, second)) => {
+			reorder_queue(ctx, &msg.into(), reorder(first, second)).await
+		}
+		Err(_) => {
+			msg.channel_id
+				.say(&ctx.http, "Parameters must be positive numbers.")
+				.await?;
+			Ok(())
+		}
+	}
+}
+
+pub(crate) async fn reorder_queue(
+	ctx: &Context,
+	source: &CommandSource<'_>,
+	reorder: Reorder,
+) -> CommandResult {
+	if !require_dj(ctx, source, "reorder the queue").await? {
+		return Ok(());
+	}
+
+	let manager = songbird::get(ctx)
+		.await
+		.expect("Songbird Voice Client placed in at initialisation.")
+		.clone();
+
+	let handler_lock = match manager.get(source.guild_id().unwrap()) {
+		Some(handler_lock) => handler_lock,
+		None => {
+			source.say(ctx, "Not playing in voice channel").await?;
+			return Ok(());
+		}
+	};
+
+	let (first, second) = match reorder {
+		Reorder::Move(first, second) | Reorder::Swap(first, second) => {
+			(first, second)
+		}
+	};
+
+	// the playing track is at the front of the queue, and songbird only moves
+	// on to the next one when the front track ends
+	if first == 0 || second == 0 {
+		source
+			.say(
+				ctx,
+				"The current song can't be moved, use `skip` to stop it or \
+				 `playnext` to play something after it.",
+			)
+			.await?;
+		return Ok(());
+	}
+
+	let handler = handler_lock.lock().await;
+	let moved = handler.queue().modify_queue(|queue| {
+		reorder.apply(queue)?;
+
+		Some((
+			queue[second].get_title().to_string(),
+			queue[first].get_title().to_string(),
+		))
+	});
+	prefetch(handler.queue());
+	drop(handler);
+
+	let message = match (moved, reorder) {
+		(None, _) => "There is no track at that position.".to_string(),
+		(Some((title, _)), Reorder::Move(..)) => MessageBuilder::new()
+			.push("Moved ")
+			.push_mono_safe(title)
+			.push(format!(" to position {}.", second))
+			.build(),
+		(Some((second_title, first_title)), Reorder::Swap(..)) => {
+			MessageBuilder::new()
+				.push("Swapped ")
+				.push_mono_safe(second_title)
+				.push(" and ")
+				.push_mono_safe(first_title)
+				.push(".")
+				.build()
+		}
+	};
+
+	source.say(ctx, message).await?;
+
+	Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+	use std::collections::VecDeque;
+
+	use super::Reorder;
+
+	fn reordered(reorder: Reorder) -> Option<Vec<u8>> {
+		let mut queue = (0..5).collect::<VecDeque<_>>();
+		reorder.apply(&mut queue)?;
+		Some(queue.into_iter().collect())
+	}
+
+	#[test]
+	fn test_reorder() {
+		assert_eq!(reordered(Reorder::Move(4, 1)), Some(vec![0, 4, 1, 2, 3]));
+		assert_eq!(reordered(Reorder::Move(1, 3)), Some(vec![0, 2, 3, 1, 4]));
+		assert_eq!(reordered(Reorder::Move(2, 2)), Some(vec![0, 1, 2, 3, 4]));
+		assert_eq!(reordered(Reorder::Swap(1, 4)), Some(vec![0, 4, 2, 3, 1]));
+		assert_eq!(reordered(Reorder::Move(5, 1)), None);
+		assert_eq!(reordered(Reorder::Swap(1, 5)), None);
+	}
+}
@@ -7,6 +7,7 @@ use serenity::{
 	utils::MessageBuilder,
 };
 
+use super::helpers::{require_dj, CommandSource};
 use crate::utils::ObtainTitle;
 
 #[command]
@@ -19,43 +20,67 @@ use crate::utils::ObtainTitle;
 #[example("2 1")]
 /// Repeat the selected track, or the current track, by the specified number of times
 async fn repeat(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
+	let parsed = if args.len() == 1 {
+		args.trimmed()
+			.parse::<usize>()
+			.map(|repeat_for| (None, repeat_for))
+			.map_err(|_| "Parameter must be an integer")
+	} else {
+		args.trimmed()
+			.parse::<usize>()
+			.and_then(|track_number| {
+				args.advance()
+					.parse::<usize>()
+					.map(|repeat_for| (Some(track_number), repeat_for))
+			})
+			.map_err(|_| "Parameters must be integers")
+	};
+
+	match parsed {
+		Ok((track_number, repeat_for)) => {
+			repeat_track(ctx, &msg.into(), track_number, repeat_for).await
+		}
+		Err(message) => {
+			msg.channel_id
+				.send_message(&ctx.http, |m| {
+					m.embed(|e| e.description(message))
+				})
+				.await?;
+			Ok(())
+		}
+	}
+}
+
+pub(crate) async fn repeat_track(
+	ctx: &Context,
+	source: &CommandSource<'_>,
+	track_number: Option<usize>,
+	repeat_for: usize,
+) -> CommandResult {
+	if !require_dj(ctx, source, "repeat songs").await? {
+		return Ok(());
+	}
+
 	let manager = songbird::get(ctx)
 		.await
 		.expect("Songbird Voice Client placed in at initialisation.")
 		.clone();
 
-	let message = match manager.get(msg.guild(&ctx.cache).await.unwrap().id) {
+	let message = match manager.get(source.guild_id().unwrap()) {
 		Some(handler_lock) => {
 			let handler = handler_lock.lock().await;
 			let queue = handler.queue();
 
-			if args.len() == 1 {
-				match args.trimmed().parse::<usize>() {
-					Ok(repeat_for) => {
-						let current_queue = queue.current_queue();
-						match current_queue.last() {
-							Some(track) => {
-								track.loop_for(repeat_for)?;
-								get_success_message(
-									track.get_title(),
-									repeat_for,
-								)
-							}
-							None => "No song to repeat.".to_string(),
-						}
+			match track_number {
+				None => match queue.current_queue().last() {
+					Some(track) => {
+						track.loop_for(repeat_for)?;
+						get_success_message(track.get_title(), repeat_for)
 					}
-					Err(_) => "Parameter must be an integer".to_string(),
-				}
-			} else {
-				match args.trimmed().parse::<usize>().and_then(|track_number| {
-					args.advance()
-						.parse::<usize>()
-						.map(|repeat_for| (track_number, repeat_for))
-				}) {
-					Ok((track_number, repeat_for)) => match queue
-						.current_queue()
-						.get(track_number)
-					{
+					None => "No song to repeat.".to_string(),
+				},
+				Some(track_number) => {
+					match queue.current_queue().get(track_number) {
 						Some(track) => {
 							track.loop_for(repeat_for)?;
 							get_success_message(track.get_title(), repeat_for)
@@ -63,17 +88,14 @@ async fn repeat(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
 						None => {
 							"There is no track at that position".to_string()
 						}
-					},
-					Err(_) => "Parameters must be integers".to_string(),
+					}
 				}
 			}
 		}
 		None => "Not playing in voice channel".to_string(),
 	};
 
-	msg.channel_id
-		.send_message(&ctx.http, |m| m.embed(|e| e.description(message)))
-		.await?;
+	source.say_embed(ctx, message).await?;
 	Ok(())
 }
 
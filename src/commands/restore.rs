@@ -0,0 +1,80 @@
+use serenity::{
+	client::Context,
+	framework::standard::{macros::command, CommandResult},
+	model::channel::Message,
+};
+use tracing::error;
+
+use super::helpers::{join_channel, CommandSource};
+use crate::{
+	library, loads::LoadJob, resolver, snapshot::QueueSnapshot,
+	utils::leave_if_empty,
+};
+
+#[command]
+#[only_in(guilds)]
+#[num_args(0)]
+/// Restores the queue that was saved when the bot last shut down. Queueing
+/// new songs first replaces it after a while
+async fn restore(ctx: &Context, msg: &Message) -> CommandResult {
+	restore_queue(ctx, &msg.into()).await
+}
+
+pub(crate) async fn restore_queue(
+	ctx: &Context,
+	source: &CommandSource<'_>,
+) -> CommandResult {
+	let guild_id = source.guild_id().unwrap();
+	let snapshot = match QueueSnapshot::load(guild_id).await {
+		Ok(Some(snapshot)) if !snapshot.tracks.is_empty() => snapshot,
+		Ok(_) => {
+			source
+				.say(ctx, "There is no saved queue to restore.")
+				.await?;
+			return Ok(());
+		}
+		Err(e) => {
+			error!("Could not read {}'s saved queue: {}", guild_id, e);
+			source.say(ctx, "Error reading the saved queue.").await?;
+			return Ok(());
+		}
+	};
+
+	let handler_lock = join_channel!(ctx, source, offer_restore = false);
+	let mut result_message = source
+		.status(
+			ctx,
+			format!(
+				"Please wait, restoring {} track(s)...",
+				snapshot.tracks.len()
+			),
+		)
+		.await?;
+
+	let mut load = LoadJob::start(ctx, source, &mut result_message).await;
+	let total = snapshot.tracks.len();
+	let song_stream =
+		snapshot.get_tracks(resolver::get(ctx).await, library::get(ctx).await);
+	match load
+		.queue(
+			ctx,
+			source,
+			&mut result_message,
+			&*handler_lock,
+			song_stream,
+			Some(total),
+		)
+		.await
+	{
+		Ok(message) => {
+			QueueSnapshot::discard(guild_id).await;
+			result_message.edit_embed(ctx, "", message).await?;
+		}
+		Err(e) => {
+			result_message.edit_text(ctx, e).await?;
+			leave_if_empty(ctx, handler_lock.lock().await, guild_id).await;
+		}
+	}
+
+	Ok(())
+}
@@ -4,13 +4,32 @@ use serenity::{
 	model::channel::Message,
 };
 
+use super::helpers::{require_dj, CommandSource};
+
 #[command]
 #[only_in(guilds)]
 #[num_args(0)]
 async fn resume(ctx: &Context, msg: &Message) -> CommandResult {
-	let guild = msg.guild(&ctx.cache).await.unwrap();
-	if guild.voice_states.get(&msg.author.id).is_none() {
-		msg.reply(ctx, "User not in voice channel").await?;
+	resume_track(ctx, &msg.into()).await
+}
+
+// kept as written before newer toolchains added this lint
+#[allow(unknown_lints, clippy::unnecessary_get_then_check)]
+pub(crate) async fn resume_track(
+	ctx: &Context,
+	source: &CommandSource<'_>,
+) -> CommandResult {
+	let guild = source
+		.guild_id()
+		.unwrap()
+		.to_guild_cached(&ctx.cache)
+		.await
+		.unwrap();
+	if guild.voice_states.get(&source.author().id).is_none() {
+		source.reply(ctx, "User not in voice channel").await?;
+		return Ok(());
+	}
+	if !require_dj(ctx, source, "resume the music").await? {
 		return Ok(());
 	}
 
@@ -25,14 +44,12 @@ async fn resume(ctx: &Context, msg: &Message) -> CommandResult {
 			match handler.queue().current() {
 				Some(track) => track.play()?,
 				None => {
-					msg.channel_id.say(&ctx.http, "Queue is empty.").await?;
+					source.say(ctx, "Queue is empty.").await?;
 				}
 			}
 		}
 		None => {
-			msg.channel_id
-				.say(&ctx.http, "Bot is not in a voice channel.")
-				.await?;
+			source.say(ctx, "Bot is not in a voice channel.").await?;
 		}
 	}
 
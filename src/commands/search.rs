@@ -1,18 +1,18 @@
-use std::{borrow::Cow, convert::TryFrom, time::Duration};
+use std::{convert::TryFrom, time::Duration};
 
 use once_cell::sync::Lazy;
-use serde::Deserialize;
-use serde_json::Deserializer;
 use serenity::{
 	client::Context,
 	framework::standard::{macros::command, Args, CommandResult},
 	model::channel::{Message, ReactionType},
+	prelude::Mutex,
 	utils::MessageBuilder,
 };
-use tokio::process::Command;
+use songbird::Call;
 use tracing::error;
 
-use crate::{join_channel, utils::*};
+use super::helpers::CommandSource;
+use crate::{join_channel, resolver, settings, utils::*};
 
 static NUMBER_REACTS: Lazy<[ReactionType; 4]> = Lazy::new(|| {
 	[
@@ -23,144 +23,125 @@ static NUMBER_REACTS: Lazy<[ReactionType; 4]> = Lazy::new(|| {
 	]
 });
 
-#[derive(Debug, Deserialize)]
-struct SearchResult<'a> {
-	#[serde(borrow)]
-	title: Cow<'a, str>,
-	#[serde(rename = "webpage_url")]
-	#[serde(borrow)]
-	url: Cow<'a, str>,
-}
-
 #[command]
 #[only_in(guilds)]
 #[min_args(1)]
 /// Search for a video on YouTube
 async fn search(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
-	let handler_lock = join_channel!(ctx, msg);
-	let mut result_message = msg
-		.channel_id
-		.say(&ctx.http, "Please wait, searching...")
-		.await?;
+	search_query(ctx, &msg.into(), args.message().trim()).await
+}
 
-	let output = Command::new("youtube-dl")
-		.arg("-R")
-		.arg("infinite")
-		.arg("--ignore-config")
-		.arg("--dump-json")
-		.arg(format!("ytsearch4:{}", args.message().trim()))
-		.output()
+pub(crate) async fn search_query(
+	ctx: &Context,
+	source: &CommandSource<'_>,
+	query: &str,
+) -> CommandResult {
+	let handler_lock = join_channel!(ctx, source);
+	let result = choose_and_queue(ctx, source, &handler_lock, query).await;
+
+	// joining stopped the idle timer, which has to start again if nothing
+	// ended up queued, however the search ended
+	leave_if_empty(ctx, handler_lock.lock().await, source.guild_id().unwrap())
 		.await;
 
-	let objects = match output {
-		Ok(objects) => objects,
+	result
+}
+
+async fn choose_and_queue(
+	ctx: &Context,
+	source: &CommandSource<'_>,
+	handler_lock: &Mutex<Call>,
+	query: &str,
+) -> CommandResult {
+	let mut result_message =
+		source.status(ctx, "Please wait, searching...").await?;
+
+	let resolver = resolver::get(ctx).await;
+	let results = match resolver.search(query, NUMBER_REACTS.len()).await {
+		Ok(results) => results,
 		Err(e) => {
-			msg.channel_id
-				.say(&ctx.http, "Error retrieving search results.")
-				.await?;
 			error!("Error retrieving search results: {:?}", e);
+			result_message.edit_text(ctx, e).await?;
 			return Ok(());
 		}
 	};
 
-	// build selection message
-	// youtube-dl's --dump-json command outputs each video as an object on one line, so the into_iter method is used to process each one
-	let results: Vec<SearchResult> = Deserializer::from_slice(&objects.stdout)
-		.into_iter()
-		.filter_map(|sr| sr.ok())
-		.collect();
-
 	// some searches don't have any results, send a different message
 	if results.is_empty() {
-		msg.channel_id
-			.say(
-				&ctx.http,
+		result_message
+			.edit_text(
+				ctx,
 				MessageBuilder::new()
 					.push("No results found for ")
-					.push_quote_safe(args.message().trim()),
+					.push_quote_safe(query),
 			)
 			.await?;
 		return Ok(());
 	}
 
+	let mut embed_message = MessageBuilder::new();
+	results.iter().enumerate().for_each(|(index, sr)| {
+		embed_message
+			.push_mono(index + 1)
+			.push(" | ")
+			.push_line_safe(sr.title.as_deref().unwrap_or("Name not present"));
+	});
 	result_message
-		.edit(&ctx.http, |m| {
-			m.content("Here are the search results:").embed(|e| {
-				let mut embed_message = MessageBuilder::new();
-
-				results.iter().enumerate().for_each(|(index, sr)| {
-					embed_message
-						.push_mono(index + 1)
-						.push(" | ")
-						.push_line_safe(&sr.title);
-				});
-
-				e.description(embed_message)
-			})
-		})
+		.edit_embed(ctx, "Here are the search results:", embed_message)
 		.await?;
 
 	// add reactions to the message
 	let results_count = results.len();
 	for emoji in NUMBER_REACTS.iter().take(results_count).cloned() {
-		result_message.react(&ctx.http, emoji).await?;
+		result_message.message().react(&ctx.http, emoji).await?;
 	}
 
 	// wait for the user to make a selection using a reaction
 	let reactions = result_message
+		.message()
 		.await_reaction(&ctx)
 		.timeout(Duration::from_secs(60))
-		.author_id(msg.author.id)
+		.author_id(source.author().id)
 		.filter(move |reaction| {
 			NUMBER_REACTS[..results_count].contains(&reaction.as_ref().emoji)
 		})
 		.await;
 
-	result_message.delete_reactions(&ctx.http).await?;
+	result_message.message().delete_reactions(&ctx.http).await?;
 	result_message
-		.edit(&ctx.http, |m| m.embed(|e| e.description("Please wait...")))
+		.edit_embed(ctx, "Here are the search results:", "Please wait...")
 		.await?;
 
-	let url = match reactions {
-		Some(reaction) => {
-			&results[NUMBER_REACTS
-				.iter()
-				.position(|number| number == &reaction.as_inner_ref().emoji)
-				.expect("Reacted to another reaction")]
-			.url
-		}
+	let track = match reactions {
+		Some(reaction) => results[NUMBER_REACTS
+			.iter()
+			.position(|number| number == &reaction.as_inner_ref().emoji)
+			.expect("Reacted to another reaction")]
+		.clone(),
 		None => {
 			result_message
-				.edit(&ctx.http, |m| {
-					m.content("One minute has passed with no selection.");
-					m.suppress_embeds(true)
-				})
+				.edit_embed(ctx, "", "One minute has passed with no selection.")
 				.await?;
 			return Ok(());
 		}
 	};
 
-	let song_stream = PlayParameter::Url(url.to_string()).get_tracks();
-	let mut handler = handler_lock.lock().await;
-	match queue_songs(&mut handler, song_stream).await {
+	// the result already has its details, so it isn't looked up again
+	let song_stream = PlayParameter::Tracks(vec![track]).get_tracks(resolver);
+	match queue_songs(
+		handler_lock,
+		song_stream,
+		source.requester(),
+		&settings::get(&ctx.data, source.guild_id().unwrap()).await,
+		None,
+	)
+	.await
+	{
 		Ok(message) => {
-			result_message
-				.edit(&ctx.http, |m| {
-					m.content("");
-					m.embed(|m| m.description(message))
-				})
-				.await?;
+			result_message.edit_embed(ctx, "", message).await?;
 		}
-		Err(message) => {
-			result_message
-				.edit(&ctx.http, |m| m.content(message))
-				.await?;
-			leave_if_empty(
-				ctx,
-				handler,
-				msg.guild(&ctx.cache).await.unwrap().id,
-			)
-			.await;
+		Err(e) => {
+			result_message.edit_text(ctx, e).await?;
 		}
 	}
 
@@ -0,0 +1,186 @@
+use std::time::Duration;
+
+use serenity::{
+	client::Context,
+	framework::standard::{macros::command, Args, CommandResult},
+	model::channel::Message,
+};
+
+use super::helpers::{require_dj, CommandSource};
+use crate::utils::{format_duration, parse_duration};
+
+/// Where to move the current track to.
+pub(crate) enum SeekTarget {
+	To(Duration),
+	Forward(Duration),
+	Rewind(Duration),
+}
+
+impl SeekTarget {
+	/// Where the track ends up from its current position, or [`None`] if that
+	/// is too far to be represented.
+	fn position(self, current: Duration) -> Option<Duration> {
+		match self {
+			Self::To(position) => Some(position),
+			Self::Forward(duration) => current.checked_add(duration),
+			Self::Rewind(duration) => Some(current.saturating_sub(duration)),
+		}
+	}
+}
+
+#[command]
+#[only_in(guilds)]
+#[num_args(1)]
+#[usage("timestamp")]
+#[example("1:23")]
+#[example("1h2m3s")]
+#[example("90")]
+/// Jumps to a point in the currently playing song
+async fn seek(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
+	parse_and_seek(ctx, msg, args, SeekTarget::To).await
+}
+
+#[command]
+#[only_in(guilds)]
+#[num_args(1)]
+#[aliases("ff")]
+#[usage("duration")]
+#[example("30")]
+#[example("1m")]
+/// Skips ahead in the currently playing song
+async fn forward(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
+	parse_and_seek(ctx, msg, args, SeekTarget::Forward).await
+}
+
+#[command]
+#[only_in(guilds)]
+#[num_args(1)]
+#[aliases("rw")]
+#[usage("duration")]
+#[example("30")]
+#[example("1m")]
+/// Goes back in the currently playing song
+async fn rewind(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
+	parse_and_seek(ctx, msg, args, SeekTarget::Rewind).await
+}
+
+async fn parse_and_seek(
+	ctx: &Context,
+	msg: &Message,
+	args: Args,
+	target: fn(Duration) -> SeekTarget,
+) -> CommandResult {
+	match parse_duration(args.message()) {
+		Some(duration) => seek_track(ctx, &msg.into(), target(duration)).await,
+		None => {
+			msg.channel_id
+				.say(
+					&ctx.http,
+					"Times must look like `1:23`, `1h2m3s` or `83`.",
+				)
+				.await?;
+			Ok(())
+		}
+	}
+}
+
+pub(crate) async fn seek_track(
+	ctx: &Context,
+	source: &CommandSource<'_>,
+	target: SeekTarget,
+) -> CommandResult {
+	if !require_dj(ctx, source, "seek in the song").await? {
+		return Ok(());
+	}
+
+	let manager = songbird::get(ctx)
+		.await
+		.expect("Songbird Voice Client placed in at initialisation.")
+		.clone();
+
+	let track = match manager.get(source.guild_id().unwrap()) {
+		Some(handler_lock) => handler_lock.lock().await.queue().current(),
+		None => None,
+	};
+	let track = match track {
+		Some(track) => track,
+		None => {
+			source.say(ctx, "No tracks in queue.").await?;
+			return Ok(());
+		}
+	};
+
+	let current = match target {
+		SeekTarget::To(_) => Duration::ZERO,
+		_ => track.get_info().await?.position,
+	};
+	let position = match target.position(current) {
+		Some(position) => position,
+		None => {
+			source.say(ctx, "Cannot skip that far ahead.").await?;
+			return Ok(());
+		}
+	};
+
+	if let Some(duration) = track.metadata().duration {
+		if position >= duration {
+			source
+				.say(
+					ctx,
+					format!(
+						"Cannot go to {}, the song is only {} long.",
+						format_duration(position),
+						format_duration(duration)
+					),
+				)
+				.await?;
+			return Ok(());
+		}
+	}
+
+	track.seek_time(position)?;
+	source
+		.say(ctx, format!("Moved to {}.", format_duration(position)))
+		.await?;
+
+	Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+	use std::time::Duration;
+
+	use super::SeekTarget;
+	use crate::utils::parse_duration;
+
+	#[test]
+	fn test_seek_position() {
+		let current = Duration::from_secs(30);
+		let huge = parse_duration("9999999999999999999h").unwrap();
+		let max = Duration::from_secs(u64::MAX);
+
+		assert_eq!(
+			SeekTarget::Forward(Duration::from_secs(10)).position(current),
+			Some(Duration::from_secs(40))
+		);
+		assert_eq!(SeekTarget::Forward(huge).position(current), None);
+		assert_eq!(SeekTarget::Forward(max).position(current), None);
+		assert_eq!(
+			SeekTarget::Forward(max).position(Duration::ZERO),
+			Some(max)
+		);
+
+		assert_eq!(
+			SeekTarget::Rewind(Duration::from_secs(10)).position(current),
+			Some(Duration::from_secs(20))
+		);
+		assert_eq!(
+			SeekTarget::Rewind(current).position(current),
+			Some(Duration::ZERO)
+		);
+		assert_eq!(
+			SeekTarget::Rewind(max).position(current),
+			Some(Duration::ZERO)
+		);
+	}
+}
@@ -8,16 +8,30 @@ use serenity::{
 };
 use songbird::tracks::TrackResult;
 
+use super::helpers::{require_dj, CommandSource};
+use crate::utils::prefetch;
+
 #[command]
 #[description = "Shuffles the current queue"]
 #[only_in(guilds)]
 async fn shuffle(ctx: &Context, msg: &Message) -> CommandResult {
+	shuffle_queue(ctx, &msg.into()).await
+}
+
+pub(crate) async fn shuffle_queue(
+	ctx: &Context,
+	source: &CommandSource<'_>,
+) -> CommandResult {
+	if !require_dj(ctx, source, "shuffle the queue").await? {
+		return Ok(());
+	}
+
 	let manager = songbird::get(ctx)
 		.await
 		.expect("Songbird Voice Client placed in at initialisation.")
 		.clone();
 
-	let message = match manager.get(msg.guild(&ctx.cache).await.unwrap().id) {
+	let message = match manager.get(source.guild_id().unwrap()) {
 		Some(handler_lock) => {
 			let handler = handler_lock.lock().await;
 
@@ -36,6 +50,7 @@ async fn shuffle(ctx: &Context, msg: &Message) -> CommandResult {
 							Ok(())
 						},
 					)?;
+					prefetch(handler.queue());
 					handler.queue().resume()?;
 					"Queue shuffled!"
 				}
@@ -44,7 +59,7 @@ async fn shuffle(ctx: &Context, msg: &Message) -> CommandResult {
 		None => "Not playing in voice channel",
 	};
 
-	msg.channel_id.say(&ctx.http, message).await?;
+	source.say(ctx, message).await?;
 
 	Ok(())
 }
@@ -1,52 +1,213 @@
+use std::{
+	collections::{HashMap, HashSet},
+	sync::Arc,
+};
+
 use serenity::{
 	client::Context,
 	framework::standard::{macros::command, Args, CommandResult},
-	model::channel::Message,
+	model::{
+		channel::Message,
+		id::{GuildId, UserId},
+	},
+	prelude::{Mutex, TypeMapKey},
+};
+use songbird::tracks::TrackHandle;
+
+use super::helpers::{get_bot_listeners, is_dj, CommandSource};
+use crate::{
+	settings,
+	utils::{get_requester, mark_dequeued, prefetch},
 };
 
+/// The users that have voted to skip the current track of each guild, along
+/// with the ID of that track so that votes are forgotten when it changes.
+pub(crate) struct SkipVotes;
+
+impl TypeMapKey for SkipVotes {
+	type Value = Arc<Mutex<HashMap<GuildId, (u128, HashSet<UserId>)>>>;
+}
+
 #[command]
 #[only_in(guilds)]
 #[max_args(1)]
-#[aliases("remove")]
-/// Skips the currently playing song.
+/// Skips the currently playing song. Without the DJ role, this votes to skip
+/// songs queued by others.
 async fn skip(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
+	if args.is_empty() {
+		return skip_track(ctx, &msg.into(), None).await;
+	}
+
+	match args.parse::<usize>() {
+		Ok(index) => skip_track(ctx, &msg.into(), Some(index)).await,
+		Err(_) => {
+			msg.channel_id
+				.say(&ctx.http, "Parameter must be a positive number.")
+				.await?;
+			Ok(())
+		}
+	}
+}
+
+pub(crate) async fn skip_track(
+	ctx: &Context,
+	source: &CommandSource<'_>,
+	index: Option<usize>,
+) -> CommandResult {
 	let manager = songbird::get(ctx)
 		.await
 		.expect("Songbird Voice Client placed in at initialisation.")
 		.clone();
 
-	let message =
-		match manager.get(msg.guild(&ctx.cache).await.unwrap().id) {
-			Some(handler_lock) => {
-				let handler = handler_lock.lock().await;
-				let queue = handler.queue();
-
-				if args.is_empty() {
-					queue.skip()?;
-					"Skipped song".to_string()
-				} else {
-					args.parse::<usize>()
-						.map(|index| {
-							queue
-								.dequeue(index)
-								.map(|track| {
-									track.stop().unwrap();
-									format!("Skipped track at position {} in queue.", index)
-								})
-								.unwrap_or(format!(
-									"No track at position {}.",
-									index
-								))
-						})
-						.unwrap_or_else(|_| {
-							"Parameter must be a positive number.".to_string()
-						})
+	let guild_id = source.guild_id().unwrap();
+	let handler_lock = match manager.get(guild_id) {
+		Some(handler_lock) => handler_lock,
+		None => {
+			source.say(ctx, "Not playing in voice channel").await?;
+			return Ok(());
+		}
+	};
+
+	let author = source.author().id;
+	let dj = is_dj(ctx, guild_id, author).await;
+	let track = {
+		let handler = handler_lock.lock().await;
+		handler
+			.queue()
+			.current_queue()
+			.get(index.unwrap_or(0))
+			.cloned()
+	};
+	let own = match track {
+		Some(ref track) => is_requester(track, author).await,
+		None => false,
+	};
+
+	let message = match index {
+		None if !dj && !own => match track {
+			Some(track) => vote_to_skip(ctx, guild_id, author, &track).await,
+			None => "Nothing is playing.".to_string(),
+		},
+		Some(_) if !dj && !own && track.is_some() => {
+			"Only members with the DJ role can skip songs queued by others, \
+			 use `skip` without a position to vote to skip the current song."
+				.to_string()
+		}
+		None => {
+			let handler = handler_lock.lock().await;
+			let current = handler.queue().current().map(|track| track.uuid());
+			if current == track.map(|track| track.uuid()) {
+				handler.queue().skip()?;
+				"Skipped song".to_string()
+			} else {
+				"The song changed, so nothing was skipped.".to_string()
+			}
+		}
+		Some(index) => {
+			// the queue may have changed since the track was checked, so the
+			// checked track is removed wherever it is now
+			let track = match track {
+				Some(track) => {
+					let handler = handler_lock.lock().await;
+					let removed = handler
+						.queue()
+						.current_queue()
+						.iter()
+						.position(|queued| queued.uuid() == track.uuid())
+						.and_then(|position| handler.queue().dequeue(position));
+					prefetch(handler.queue());
+					removed
+				}
+				None => None,
+			};
+			match track {
+				Some(track) => {
+					mark_dequeued(&track).await;
+					track.stop().unwrap();
+					format!("Skipped track at position {} in queue.", index)
 				}
+				None => format!("No track at position {}.", index),
 			}
-			None => "Not playing in voice channel".to_string(),
-		};
+		}
+	};
 
-	msg.channel_id.say(&ctx.http, message).await?;
+	source.say(ctx, message).await?;
 
 	Ok(())
 }
+
+async fn is_requester(track: &TrackHandle, user: UserId) -> bool {
+	get_requester(track)
+		.await
+		.map_or(false, |requester| requester.user == user)
+}
+
+/// The number of votes needed to skip with this many listeners, at least one.
+fn votes_needed(listeners: usize, ratio: f64) -> usize {
+	((listeners as f64 * ratio).ceil() as usize).max(1)
+}
+
+/// Adds a vote to skip the current track, skipping it once enough of the
+/// listeners have voted.
+async fn vote_to_skip(
+	ctx: &Context,
+	guild_id: GuildId,
+	user: UserId,
+	track: &TrackHandle,
+) -> String {
+	let listeners = get_bot_listeners(ctx, guild_id).await;
+	if !listeners.contains(&user) {
+		return "You must be in the voice channel to vote to skip.".to_string();
+	}
+
+	let ratio = settings::get(&ctx.data, guild_id).await.skip_ratio;
+	let needed = votes_needed(listeners.len(), ratio);
+
+	let votes = ctx
+		.data
+		.read()
+		.await
+		.get::<SkipVotes>()
+		.expect("Skip votes placed in at initialisation.")
+		.clone();
+	let mut votes = votes.lock().await;
+
+	let uuid = track.uuid().as_u128();
+	let (voted_track, voters) = votes
+		.entry(guild_id)
+		.or_insert_with(|| (uuid, HashSet::new()));
+	if *voted_track != uuid {
+		*voted_track = uuid;
+		voters.clear();
+	}
+	voters.insert(user);
+
+	// people that voted and then left the channel no longer count
+	let count = voters
+		.iter()
+		.filter(|voter| listeners.contains(voter))
+		.count();
+	if count < needed {
+		return format!("Voted to skip, {} of {} votes needed.", count, needed);
+	}
+
+	votes.remove(&guild_id);
+	match track.stop() {
+		Ok(_) => "Vote passed, skipped song.".to_string(),
+		Err(_) => "The song has already ended.".to_string(),
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::votes_needed;
+
+	#[test]
+	fn test_votes_needed() {
+		assert_eq!(votes_needed(1, 0.5), 1);
+		assert_eq!(votes_needed(4, 0.5), 2);
+		assert_eq!(votes_needed(5, 0.5), 3);
+		assert_eq!(votes_needed(3, 1.0), 3);
+		assert_eq!(votes_needed(10, 0.0), 1);
+	}
+}
@@ -0,0 +1,94 @@
+use serenity::{
+	client::Context,
+	framework::standard::{macros::command, Args, CommandResult},
+	model::{channel::Message, id::GuildId},
+};
+use tracing::error;
+
+use super::helpers::{require_dj, CommandSource};
+use crate::{
+	idle::{Idle, IdleReason},
+	settings,
+};
+
+#[command]
+#[only_in(guilds)]
+#[max_args(1)]
+#[aliases("247")]
+#[usage("[on|off]")]
+#[example("on")]
+/// Shows or changes whether the bot stays in the voice channel when the queue
+/// is empty or nobody is listening, known as 24/7 mode
+async fn stay(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
+	match args.message().trim().to_lowercase().as_str() {
+		"" => set_always_on(ctx, &msg.into(), None).await,
+		"on" => set_always_on(ctx, &msg.into(), Some(true)).await,
+		"off" => set_always_on(ctx, &msg.into(), Some(false)).await,
+		_ => {
+			msg.channel_id
+				.say(&ctx.http, "Choose either `on` or `off`.")
+				.await?;
+			Ok(())
+		}
+	}
+}
+
+pub(crate) async fn set_always_on(
+	ctx: &Context,
+	source: &CommandSource<'_>,
+	always_on: Option<bool>,
+) -> CommandResult {
+	let guild_id = source.guild_id().unwrap();
+
+	let always_on = match always_on {
+		Some(always_on) => always_on,
+		None => {
+			let always_on = settings::get(&ctx.data, guild_id).await.always_on;
+			source.say(ctx, status(always_on)).await?;
+			return Ok(());
+		}
+	};
+
+	if !require_dj(ctx, source, "change 24/7 mode").await? {
+		return Ok(());
+	}
+
+	if let Err(e) = settings::update(&ctx.data, guild_id, |settings| {
+		settings.always_on = always_on
+	})
+	.await
+	{
+		error!("Could not save settings of {}: {}", guild_id, e);
+		source.say(ctx, "Could not save the setting.").await?;
+		return Ok(());
+	}
+
+	apply_always_on(ctx, guild_id, always_on).await;
+	source.say(ctx, status(always_on)).await?;
+
+	Ok(())
+}
+
+/// Stops the timers for leaving when 24/7 mode is turned on, and starts any
+/// that apply when it is turned off.
+pub(crate) async fn apply_always_on(
+	ctx: &Context,
+	guild_id: GuildId,
+	always_on: bool,
+) {
+	let idle = Idle::from_context(ctx).await;
+	if always_on {
+		idle.stop(guild_id, IdleReason::QueueEmpty).await;
+		idle.stop(guild_id, IdleReason::Alone).await;
+	} else {
+		idle.refresh(guild_id).await;
+	}
+}
+
+fn status(always_on: bool) -> &'static str {
+	if always_on {
+		"24/7 mode is on, the bot will stay until stopped."
+	} else {
+		"24/7 mode is off, the bot will leave when idle."
+	}
+}
@@ -1,33 +1,34 @@
-use std::time::Duration;
-
 use serenity::{
 	client::Context,
 	framework::standard::{macros::command, CommandResult},
 	model::channel::Message,
 };
-use tokio::time::sleep;
-use tracing::warn;
 
-use crate::utils::get_user_server_channel;
+use super::helpers::{require_dj, CommandSource};
+use crate::{idle::Idle, utils::get_user_server_channel};
 
 #[command]
 #[only_in(guilds)]
 #[num_args(0)]
-/// Stops and disconnects the bot from the voice channel
+/// Stops and disconnects the bot from the voice channel, cancelling any songs
+/// still being loaded
 async fn stop(ctx: &Context, msg: &Message) -> CommandResult {
-	if let Some((guild, _)) = get_user_server_channel(ctx, msg).await {
-		let manager = songbird::get(ctx)
-			.await
-			.expect("Songbird Voice Client placed in at initialisation.")
-			.clone();
+	stop_playing(ctx, &msg.into()).await
+}
 
-		while let Err(e) = manager.remove(guild).await {
-			warn!(
-				"Could not leave voice channel: {}, trying again in 5 seconds",
-				e
-			);
-			sleep(Duration::from_secs(5)).await;
-		}
+pub(crate) async fn stop_playing(
+	ctx: &Context,
+	source: &CommandSource<'_>,
+) -> CommandResult {
+	if !require_dj(ctx, source, "stop the music").await? {
+		return Ok(());
+	}
+
+	if let Some((guild, _)) =
+		get_user_server_channel(ctx, source.guild_id(), source.author().id)
+			.await
+	{
+		Idle::from_context(ctx).await.leave(guild).await;
 	}
 	Ok(())
 }
@@ -5,6 +5,8 @@ use serenity::{
 };
 use tracing::error;
 
+use super::helpers::CommandSource;
+
 // Dicord automatically changes the tabs to four spaces, so extra spaces are added to fix alignment
 const VERSION_TEXT: &str = concat!(
 	"```",
@@ -24,7 +26,14 @@ const VERSION_TEXT: &str = concat!(
 #[command]
 /// Get the build details of the bot
 async fn version(ctx: &Context, msg: &Message) -> CommandResult {
-	if let Err(e) = msg.channel_id.say(&ctx.http, VERSION_TEXT).await {
+	send_version(ctx, &msg.into()).await
+}
+
+pub(crate) async fn send_version(
+	ctx: &Context,
+	source: &CommandSource<'_>,
+) -> CommandResult {
+	if let Err(e) = source.say(ctx, VERSION_TEXT).await {
 		error!("Error printing version: {}", e);
 	};
 
@@ -0,0 +1,104 @@
+use serenity::{
+	client::Context,
+	framework::standard::{macros::command, Args, CommandResult},
+	model::{channel::Message, id::GuildId},
+};
+use tracing::error;
+
+use super::helpers::{require_dj, CommandSource};
+use crate::settings::{self, MAX_VOLUME};
+
+#[command]
+#[only_in(guilds)]
+#[max_args(1)]
+#[aliases("vol")]
+#[usage("[percent]")]
+#[example("50")]
+/// Shows or changes the volume of every song, from 0 to 200 percent
+async fn volume(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
+	if args.is_empty() {
+		return set_volume(ctx, &msg.into(), None).await;
+	}
+
+	match args.single::<u16>() {
+		Ok(volume) => set_volume(ctx, &msg.into(), Some(volume)).await,
+		Err(_) => {
+			msg.channel_id
+				.say(
+					&ctx.http,
+					format!("Volume must be between 0 and {}.", MAX_VOLUME),
+				)
+				.await?;
+			Ok(())
+		}
+	}
+}
+
+pub(crate) async fn set_volume(
+	ctx: &Context,
+	source: &CommandSource<'_>,
+	volume: Option<u16>,
+) -> CommandResult {
+	let guild_id = source.guild_id().unwrap();
+
+	let volume = match volume {
+		None => {
+			let volume = settings::get(&ctx.data, guild_id).await.volume;
+			source.say(ctx, format!("Volume is {}%.", volume)).await?;
+			return Ok(());
+		}
+		Some(volume) if volume > MAX_VOLUME => {
+			source
+				.say(
+					ctx,
+					format!("Volume must be between 0 and {}.", MAX_VOLUME),
+				)
+				.await?;
+			return Ok(());
+		}
+		Some(volume) => volume,
+	};
+
+	if !require_dj(ctx, source, "change the volume").await? {
+		return Ok(());
+	}
+
+	let settings = match settings::update(&ctx.data, guild_id, |settings| {
+		settings.volume = volume
+	})
+	.await
+	{
+		Ok(settings) => settings,
+		Err(e) => {
+			error!("Could not save settings of {}: {}", guild_id, e);
+			source.say(ctx, "Could not save the volume.").await?;
+			return Ok(());
+		}
+	};
+
+	apply_volume(ctx, guild_id, settings.volume_multiplier()).await;
+	source
+		.say(ctx, format!("Volume set to {}%.", volume))
+		.await?;
+
+	Ok(())
+}
+
+/// Changes the volume of the tracks already in the queue, which were created
+/// with the old volume.
+pub(crate) async fn apply_volume(
+	ctx: &Context,
+	guild_id: GuildId,
+	volume: f32,
+) {
+	let manager = songbird::get(ctx)
+		.await
+		.expect("Songbird Voice Client placed in at initialisation.")
+		.clone();
+
+	if let Some(handler_lock) = manager.get(guild_id) {
+		for track in handler_lock.lock().await.queue().current_queue() {
+			let _ = track.set_volume(volume);
+		}
+	}
+}
@@ -0,0 +1,430 @@
+use std::{
+	env, fmt, fs, io,
+	path::PathBuf,
+	str::FromStr,
+	sync::{Arc, RwLock},
+	time::Duration,
+};
+
+use once_cell::sync::Lazy;
+use serde::Deserialize;
+use tracing::{info, warn};
+
+/// The file read when `MUSICBOT_CONFIG` doesn't name another one.
+const DEFAULT_PATH: &str = "musicbot.toml";
+/// The longest idle or alone timeout, a week in minutes.
+const MAX_TIMEOUT: u64 = 7 * 24 * 60;
+
+/// Which resolver finds and plays tracks.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub(crate) enum ResolverKind {
+	YoutubeDl,
+	/// Plays files from `library_dir`.
+	Local,
+	/// Plays silence, for testing.
+	Fake,
+}
+
+impl FromStr for ResolverKind {
+	type Err = ();
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		match s {
+			"youtube-dl" => Ok(Self::YoutubeDl),
+			"local" => Ok(Self::Local),
+			"fake" => Ok(Self::Fake),
+			_ => Err(()),
+		}
+	}
+}
+
+/// Settings for the whole bot, read from a TOML file at startup. Each can be
+/// overridden by the environment variable named next to it.
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub(crate) struct Config {
+	/// `DISCORD_TOKEN`
+	pub token: String,
+	/// `MUSICBOT_PREFIX`, the command prefix where a guild hasn't set one.
+	pub prefix: String,
+	/// `RUSTY_QUEUE_CHUNK_SIZE`, how many tracks of a playlist are loaded at
+	/// the same time.
+	pub queue_chunk_size: usize,
+	/// `MUSICBOT_QUEUE_PAGE_SIZE`, from 1 to 25. Pages with long titles are
+	/// cut short to fit in an embed.
+	pub queue_page_size: usize,
+	/// `MUSICBOT_PREFETCH_TRACKS`, how many tracks after the current one
+	/// start loading before they play.
+	pub prefetch_tracks: usize,
+	/// `MUSICBOT_DATA_DIR`
+	pub data_dir: PathBuf,
+	/// `MUSICBOT_SNAPSHOT_INTERVAL`, in seconds.
+	pub snapshot_interval: u64,
+	/// `MUSICBOT_IDLE_TIMEOUT`, in minutes.
+	pub idle_timeout: u64,
+	/// `MUSICBOT_ALONE_TIMEOUT`, in minutes.
+	pub alone_timeout: u64,
+	/// `MUSICBOT_RESOLVER`
+	pub resolver: ResolverKind,
+	/// `MUSICBOT_YTDL_PATH`, which can also be a fork such as yt-dlp.
+	pub ytdl_path: String,
+	/// `MUSICBOT_YTDL_ARGS`, separated by whitespace in the variable.
+	pub ytdl_args: Vec<String>,
+	/// `MUSICBOT_LIBRARY_DIR`, the music library, which is also where the
+	/// local resolver plays from.
+	pub library_dir: Option<PathBuf>,
+}
+
+impl Default for Config {
+	fn default() -> Self {
+		Self {
+			token: String::new(),
+			prefix: "~".into(),
+			queue_chunk_size: 20,
+			queue_page_size: 10,
+			prefetch_tracks: 2,
+			data_dir: "data".into(),
+			snapshot_interval: 30,
+			idle_timeout: 0,
+			alone_timeout: 5,
+			resolver: ResolverKind::YoutubeDl,
+			ytdl_path: "youtube-dl".into(),
+			ytdl_args: Vec::new(),
+			library_dir: None,
+		}
+	}
+}
+
+/// Why the configuration could not be loaded.
+#[derive(Debug)]
+pub(crate) enum LoadError {
+	Read(PathBuf, io::Error),
+	Parse(PathBuf, toml::de::Error),
+	/// An environment variable that could not be parsed.
+	Variable(&'static str, String),
+	Invalid(String),
+}
+
+impl fmt::Display for LoadError {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			Self::Read(path, e) => write!(f, "Cannot read {:?}: {}", path, e),
+			Self::Parse(path, e) => write!(f, "Cannot parse {:?}: {}", path, e),
+			Self::Variable(name, value) => {
+				write!(f, "{} has an invalid value {:?}", name, value)
+			}
+			Self::Invalid(message) => f.write_str(message),
+		}
+	}
+}
+
+impl std::error::Error for LoadError {}
+
+impl Config {
+	/// Reads the configuration file named by `MUSICBOT_CONFIG`, which only has
+	/// to exist if the variable is set, then applies the environment.
+	pub(crate) fn load() -> Result<Self, LoadError> {
+		let (path, required) = match env::var_os("MUSICBOT_CONFIG") {
+			Some(path) => (PathBuf::from(path), true),
+			None => (PathBuf::from(DEFAULT_PATH), false),
+		};
+
+		let file = match fs::read_to_string(&path) {
+			Ok(file) => {
+				info!("Reading configuration from {:?}", path);
+				Some(file)
+			}
+			Err(e) if e.kind() == io::ErrorKind::NotFound && !required => None,
+			Err(e) => return Err(LoadError::Read(path, e)),
+		};
+
+		let config = match file {
+			Some(file) => {
+				toml::from_str(&file).map_err(|e| LoadError::Parse(path, e))?
+			}
+			None => Self::default(),
+		};
+
+		config.with_env(|name| env::var(name).ok())
+	}
+
+	/// Overrides the configuration with the variables that are set, then
+	/// checks the result.
+	fn with_env(
+		mut self,
+		var: impl Fn(&str) -> Option<String>,
+	) -> Result<Self, LoadError> {
+		fn parse<T: FromStr>(
+			name: &'static str,
+			value: String,
+		) -> Result<T, LoadError> {
+			value
+				.trim()
+				.parse()
+				.map_err(|_| LoadError::Variable(name, value))
+		}
+
+		if let Some(token) = var("DISCORD_TOKEN") {
+			self.token = token;
+		}
+		if let Some(prefix) = var("MUSICBOT_PREFIX") {
+			self.prefix = prefix;
+		}
+		if let Some(size) = var("RUSTY_QUEUE_CHUNK_SIZE") {
+			self.queue_chunk_size = parse("RUSTY_QUEUE_CHUNK_SIZE", size)?;
+		}
+		if let Some(size) = var("MUSICBOT_QUEUE_PAGE_SIZE") {
+			self.queue_page_size = parse("MUSICBOT_QUEUE_PAGE_SIZE", size)?;
+		}
+		if let Some(count) = var("MUSICBOT_PREFETCH_TRACKS") {
+			self.prefetch_tracks = parse("MUSICBOT_PREFETCH_TRACKS", count)?;
+		}
+		if let Some(dir) = var("MUSICBOT_DATA_DIR") {
+			self.data_dir = dir.into();
+		}
+		if let Some(secs) = var("MUSICBOT_SNAPSHOT_INTERVAL") {
+			self.snapshot_interval = parse("MUSICBOT_SNAPSHOT_INTERVAL", secs)?;
+		}
+		if let Some(minutes) = var("MUSICBOT_IDLE_TIMEOUT") {
+			self.idle_timeout = parse("MUSICBOT_IDLE_TIMEOUT", minutes)?;
+		}
+		if let Some(minutes) = var("MUSICBOT_ALONE_TIMEOUT") {
+			self.alone_timeout = parse("MUSICBOT_ALONE_TIMEOUT", minutes)?;
+		}
+		if let Some(resolver) = var("MUSICBOT_RESOLVER") {
+			self.resolver = parse("MUSICBOT_RESOLVER", resolver)?;
+		}
+		if let Some(path) = var("MUSICBOT_YTDL_PATH") {
+			self.ytdl_path = path;
+		}
+		if let Some(args) = var("MUSICBOT_YTDL_ARGS") {
+			self.ytdl_args =
+				args.split_whitespace().map(str::to_string).collect();
+		}
+		if let Some(dir) = var("MUSICBOT_LIBRARY_DIR") {
+			self.library_dir = Some(dir.into());
+		}
+
+		self.validate()?;
+		Ok(self)
+	}
+
+	fn validate(&self) -> Result<(), LoadError> {
+		let invalid = |message: &str| Err(LoadError::Invalid(message.into()));
+
+		if self.token.is_empty() {
+			return invalid(
+				"No Discord token, set DISCORD_TOKEN or `token` in the \
+				 configuration file",
+			);
+		}
+		if self.prefix.is_empty() || self.prefix.contains(char::is_whitespace) {
+			return invalid("The prefix must not be empty or contain spaces");
+		}
+		if self.queue_chunk_size == 0 {
+			return invalid("The queue chunk size must be at least 1");
+		}
+		if !(1..=25).contains(&self.queue_page_size) {
+			return invalid("The queue page size must be from 1 to 25");
+		}
+		if self.snapshot_interval == 0 {
+			return invalid("The snapshot interval must be at least 1 second");
+		}
+		if self.idle_timeout > MAX_TIMEOUT || self.alone_timeout > MAX_TIMEOUT {
+			return invalid(
+				"The timeouts must be at most a week (10080 minutes)",
+			);
+		}
+		if self.resolver == ResolverKind::Local && self.library_dir.is_none() {
+			return invalid(
+				"The local resolver plays from the music library, set \
+				 MUSICBOT_LIBRARY_DIR or `library_dir` in the configuration file",
+			);
+		}
+		if self.ytdl_path.is_empty() {
+			return invalid("The youtube-dl path must not be empty");
+		}
+
+		Ok(())
+	}
+
+	/// Takes the settings that can change while the bot runs from a newly
+	/// loaded configuration, warning about any others that changed.
+	fn reload_from(&self, new: Self) -> Self {
+		let kept = Self {
+			prefix: new.prefix.clone(),
+			queue_chunk_size: new.queue_chunk_size,
+			queue_page_size: new.queue_page_size,
+			prefetch_tracks: new.prefetch_tracks,
+			idle_timeout: new.idle_timeout,
+			alone_timeout: new.alone_timeout,
+			..self.clone()
+		};
+
+		if kept != new {
+			warn!(
+				"Only the prefix, queue sizes, prefetching and timeouts are \
+				 reloaded, the other changes need a restart"
+			);
+		}
+
+		kept
+	}
+
+	pub(crate) fn idle_timeout(&self) -> Duration {
+		Duration::from_secs(self.idle_timeout * 60)
+	}
+
+	pub(crate) fn alone_timeout(&self) -> Duration {
+		Duration::from_secs(self.alone_timeout * 60)
+	}
+}
+
+static CONFIG: Lazy<RwLock<Arc<Config>>> = Lazy::new(Default::default);
+
+/// The current configuration, which can change when it is reloaded.
+pub(crate) fn get() -> Arc<Config> {
+	CONFIG.read().unwrap().clone()
+}
+
+/// Makes a configuration the current one, which should be done once at
+/// startup before [`get`] is used.
+pub(crate) fn set(config: Config) {
+	*CONFIG.write().unwrap() = Arc::new(config);
+}
+
+/// Loads the configuration again, keeping the current one if it is invalid.
+pub(crate) fn reload() -> Result<(), LoadError> {
+	let new = Config::load()?;
+	let mut config = CONFIG.write().unwrap();
+	*config = Arc::new(config.reload_from(new));
+
+	Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+	use std::{collections::HashMap, time::Duration};
+
+	use super::{Config, LoadError, ResolverKind};
+
+	fn with_env(
+		file: &str,
+		vars: &[(&str, &str)],
+	) -> Result<Config, LoadError> {
+		let vars = vars
+			.iter()
+			.map(|(name, value)| (name.to_string(), value.to_string()))
+			.collect::<HashMap<_, _>>();
+
+		toml::from_str::<Config>(file)
+			.unwrap()
+			.with_env(|name| vars.get(name).cloned())
+	}
+
+	#[test]
+	fn test_env_overrides_file() {
+		let config = with_env(
+			"token = \"file\"\nprefix = \"!\"\nresolver = \"local\"\n\
+			 library_dir = \"music\"",
+			&[("DISCORD_TOKEN", "env"), ("MUSICBOT_QUEUE_PAGE_SIZE", "5")],
+		)
+		.unwrap();
+
+		assert_eq!(config.token, "env");
+		assert_eq!(config.prefix, "!");
+		assert_eq!(config.queue_page_size, 5);
+		assert_eq!(config.resolver, ResolverKind::Local);
+		assert_eq!(config.queue_chunk_size, 20);
+	}
+
+	#[test]
+	fn test_timeouts() {
+		let config = with_env("", &[("DISCORD_TOKEN", "token")]).unwrap();
+		assert_eq!(config.idle_timeout(), Duration::ZERO);
+		assert_eq!(config.alone_timeout(), Duration::from_secs(5 * 60));
+
+		let config = with_env(
+			"idle_timeout = 10",
+			&[("DISCORD_TOKEN", "token"), ("MUSICBOT_ALONE_TIMEOUT", "1")],
+		)
+		.unwrap();
+		assert_eq!(config.idle_timeout(), Duration::from_secs(10 * 60));
+		assert_eq!(config.alone_timeout(), Duration::from_secs(60));
+	}
+
+	#[test]
+	fn test_ytdl_settings() {
+		let config = with_env(
+			"ytdl_path = \"yt-dlp\"\nytdl_args = [\"--no-check-certificate\"]",
+			&[("DISCORD_TOKEN", "token")],
+		)
+		.unwrap();
+		assert_eq!(config.ytdl_path, "yt-dlp");
+		assert_eq!(config.ytdl_args, ["--no-check-certificate"]);
+
+		let config = with_env(
+			"",
+			&[
+				("DISCORD_TOKEN", "token"),
+				("MUSICBOT_YTDL_ARGS", " --proxy  socks5://proxy:1080 "),
+			],
+		)
+		.unwrap();
+		assert_eq!(config.ytdl_path, "youtube-dl");
+		assert_eq!(config.ytdl_args, ["--proxy", "socks5://proxy:1080"]);
+
+		assert!(matches!(
+			with_env("ytdl_path = \"\"", &[("DISCORD_TOKEN", "token")]),
+			Err(LoadError::Invalid(_))
+		));
+	}
+
+	#[test]
+	fn test_invalid_values() {
+		let token = [("DISCORD_TOKEN", "token")];
+
+		assert!(matches!(
+			with_env("", &[("RUSTY_QUEUE_CHUNK_SIZE", "lots"), token[0]]),
+			Err(LoadError::Variable("RUSTY_QUEUE_CHUNK_SIZE", _))
+		));
+		assert!(matches!(
+			with_env("queue_page_size = 30", &token),
+			Err(LoadError::Invalid(_))
+		));
+		assert!(matches!(
+			with_env("resolver = \"local\"", &token),
+			Err(LoadError::Invalid(_))
+		));
+		assert!(matches!(
+			with_env("alone_timeout = 10081", &token),
+			Err(LoadError::Invalid(_))
+		));
+		assert!(matches!(
+			with_env(
+				"",
+				&[("MUSICBOT_IDLE_TIMEOUT", "18446744073709551615"), token[0]]
+			),
+			Err(LoadError::Invalid(_))
+		));
+		assert!(with_env("idle_timeout = 10080", &token).is_ok());
+		assert!(matches!(with_env("", &[]), Err(LoadError::Invalid(_))));
+		assert!(toml::from_str::<Config>("unknown = 1").is_err());
+	}
+
+	#[test]
+	fn test_reload_keeps_startup_settings() {
+		let current = with_env("", &[("DISCORD_TOKEN", "old")]).unwrap();
+		let new = with_env(
+			"prefix = \"!\"\ndata_dir = \"elsewhere\"",
+			&[("DISCORD_TOKEN", "new")],
+		)
+		.unwrap();
+
+		let reloaded = current.reload_from(new);
+		assert_eq!(reloaded.prefix, "!");
+		assert_eq!(reloaded.token, "old");
+		assert_eq!(reloaded.data_dir, current.data_dir);
+	}
+}
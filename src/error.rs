@@ -0,0 +1,282 @@
+use std::{fmt, io};
+
+use songbird::{error::JoinError, input::error::Error as InputError};
+
+use crate::{
+	playlist::{MAX_NAME_LENGTH, MAX_PLAYLISTS, MAX_TRACKS},
+	resolver::TrackDescriptor,
+};
+
+pub(crate) type Result<T> = std::result::Result<T, Error>;
+
+/// Everything that can go wrong while finding, loading or playing tracks.
+///
+/// The [`Display`](fmt::Display) implementation is meant to be shown to users,
+/// while the [`Debug`] implementation keeps the details for the logs.
+#[derive(Debug)]
+pub(crate) enum Error {
+	/// The youtube-dl executable could not be found.
+	ExtractorMissing(String),
+	/// youtube-dl exited unsuccessfully, with the given error output.
+	ExtractorFailed(String),
+	/// The video has been removed, or never existed.
+	Unavailable,
+	/// The video is private.
+	Private,
+	/// The video requires signing in to confirm the viewer's age.
+	AgeRestricted,
+	/// The video is not available in the country the bot runs in.
+	GeoBlocked,
+	/// A search or playlist did not contain anything.
+	NoResults,
+	/// The queue already holds the most tracks allowed in the guild.
+	QueueFull(usize),
+	/// Loading was cancelled before any track was queued.
+	Cancelled,
+	/// A path led outside of the music library.
+	OutsideLibrary,
+	/// There is no playlist with the name.
+	PlaylistNotFound(String),
+	InvalidPlaylistName,
+	InvalidLink(String),
+	/// An imported file was neither JSON nor a list of links.
+	InvalidPlaylistFile,
+	/// The playlist has no track at the position, counting from 1.
+	NoPlaylistPosition(usize),
+	/// A playlist of the guild shared by another user, who along with DJs
+	/// is the only one able to change it.
+	PlaylistSharedByOther(String),
+	EmptyPlaylist,
+	PlaylistTooLong,
+	TooManyPlaylists,
+	/// The bot could not join the voice channel.
+	VoiceJoin(Box<JoinError>),
+	/// The audio of a track could not be created.
+	Input(InputError),
+	/// A file could not be read, or a program could not be started.
+	Io(io::Error),
+	/// A track could not be loaded, keeping which track it was.
+	Track(TrackDescriptor, Box<Error>),
+}
+
+impl Error {
+	/// Works out what went wrong from the error output of youtube-dl.
+	pub(crate) fn from_extractor_output(stderr: &[u8]) -> Self {
+		let stderr = String::from_utf8_lossy(stderr);
+		let lowercase = stderr.to_lowercase();
+		let contains_any =
+			|patterns: &[&str]| patterns.iter().any(|p| lowercase.contains(p));
+
+		if contains_any(&["private video"]) {
+			Self::Private
+		} else if contains_any(&["confirm your age", "age-restricted"]) {
+			Self::AgeRestricted
+		} else if contains_any(&["in your country", "geo restriction"]) {
+			Self::GeoBlocked
+		} else if contains_any(&["video unavailable", "is unavailable"]) {
+			Self::Unavailable
+		} else {
+			// the last error line is the most useful, everything before it is
+			// usually progress output
+			let message = stderr
+				.lines()
+				.rev()
+				.find(|line| line.starts_with("ERROR"))
+				.or_else(|| stderr.lines().next_back())
+				.unwrap_or("no error output")
+				.to_string();
+			Self::ExtractorFailed(message)
+		}
+	}
+
+	/// Attaches the track that was being loaded when the error occurred.
+	pub(crate) fn for_track(self, track: &TrackDescriptor) -> Self {
+		match self {
+			Self::Track(..) => self,
+			error => Self::Track(track.clone(), Box::new(error)),
+		}
+	}
+}
+
+impl fmt::Display for Error {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			Self::ExtractorMissing(executable) => write!(
+				f,
+				"The downloader `{}` is not installed, please let the bot \
+				 owner know.",
+				executable
+			),
+			Self::ExtractorFailed(message) => {
+				write!(f, "The download failed: {}", message)
+			}
+			Self::Unavailable => write!(f, "This video is unavailable."),
+			Self::Private => write!(f, "This video is private."),
+			Self::AgeRestricted => write!(f, "This video is age restricted."),
+			Self::GeoBlocked => {
+				write!(f, "This video is not available in the bot's country.")
+			}
+			Self::NoResults => write!(f, "Nothing was found."),
+			Self::QueueFull(max) => {
+				write!(
+					f,
+					"The queue is full, it can hold {} songs at most.",
+					max
+				)
+			}
+			Self::Cancelled => write!(f, "Loading was cancelled."),
+			Self::OutsideLibrary => {
+				write!(f, "Only files inside the music library can be played.")
+			}
+			Self::PlaylistNotFound(name) => {
+				write!(f, "There is no playlist called `{}`.", name)
+			}
+			Self::InvalidPlaylistName => write!(
+				f,
+				"Playlist names must be up to {} letters, numbers, `-` or `_`.",
+				MAX_NAME_LENGTH
+			),
+			Self::InvalidLink(link) => write!(f, "`{}` is not a link.", link),
+			Self::InvalidPlaylistFile => write!(
+				f,
+				"The playlist must be exported JSON or a list of links."
+			),
+			Self::NoPlaylistPosition(position) => {
+				write!(f, "The playlist has no song at position {}.", position)
+			}
+			Self::PlaylistSharedByOther(name) => write!(
+				f,
+				"`{}` was shared by someone else, so only they or a DJ can \
+				 change it.",
+				name
+			),
+			Self::EmptyPlaylist => write!(f, "The playlist has no songs."),
+			Self::PlaylistTooLong => {
+				write!(f, "Playlists can hold {} songs at most.", MAX_TRACKS)
+			}
+			Self::TooManyPlaylists => {
+				write!(f, "There can be {} playlists at most.", MAX_PLAYLISTS)
+			}
+			Self::VoiceJoin(_) => write!(f, "Error joining the channel."),
+			Self::Input(e) => write!(f, "The audio could not be loaded: {}", e),
+			Self::Io(e) => write!(f, "The file could not be read: {}", e),
+			Self::Track(track, error) => write!(
+				f,
+				"{}: {}",
+				track.title.as_deref().unwrap_or(&track.url),
+				error
+			),
+		}
+	}
+}
+
+impl std::error::Error for Error {
+	fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+		match self {
+			Self::VoiceJoin(e) => Some(e.as_ref()),
+			Self::Input(e) => Some(e),
+			Self::Io(e) => Some(e),
+			Self::Track(_, e) => Some(e),
+			_ => None,
+		}
+	}
+}
+
+impl From<InputError> for Error {
+	fn from(e: InputError) -> Self {
+		match e {
+			InputError::YouTubeDlRun(output) => {
+				Self::from_extractor_output(&output.stderr)
+			}
+			InputError::Io(e) => Self::Io(e),
+			e => Self::Input(e),
+		}
+	}
+}
+
+impl From<io::Error> for Error {
+	fn from(e: io::Error) -> Self {
+		Self::Io(e)
+	}
+}
+
+impl From<JoinError> for Error {
+	fn from(e: JoinError) -> Self {
+		Self::VoiceJoin(Box::new(e))
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::io;
+
+	use songbird::input::error::Error as InputError;
+
+	use super::Error;
+	use crate::resolver::TrackDescriptor;
+
+	#[test]
+	fn test_from_extractor_output() {
+		let error =
+			|stderr: &str| Error::from_extractor_output(stderr.as_bytes());
+
+		assert!(matches!(
+			error("ERROR: [youtube] abc: Private video"),
+			Error::Private
+		));
+		assert!(matches!(
+			error("ERROR: Sign in to confirm your age"),
+			Error::AgeRestricted
+		));
+		assert!(matches!(
+			error("ERROR: The uploader has not made this video available in your country"),
+			Error::GeoBlocked
+		));
+		assert!(matches!(
+			error("ERROR: Video unavailable"),
+			Error::Unavailable
+		));
+		assert!(matches!(
+			error("[download] 10%\nERROR: HTTP Error 429\nretrying"),
+			Error::ExtractorFailed(message) if message == "ERROR: HTTP Error 429"
+		));
+		assert!(matches!(
+			error(""),
+			Error::ExtractorFailed(message) if message == "no error output"
+		));
+	}
+
+	#[test]
+	fn test_for_track() {
+		let track = TrackDescriptor {
+			title: Some("Song".to_string()),
+			..TrackDescriptor::from_url("https://example.com/1")
+		};
+		let other = TrackDescriptor::from_url("https://example.com/2");
+
+		// the first track to be attached is the one that failed
+		let error = Error::Private.for_track(&track).for_track(&other);
+		assert_eq!(error.to_string(), "Song: This video is private.");
+
+		let error = Error::Unavailable.for_track(&other);
+		assert_eq!(
+			error.to_string(),
+			"https://example.com/2: This video is unavailable."
+		);
+	}
+
+	#[test]
+	fn test_from_input_error() {
+		let io_error = || io::Error::new(io::ErrorKind::NotFound, "missing");
+
+		assert!(matches!(
+			Error::from(InputError::Io(io_error())),
+			Error::Io(_)
+		));
+		assert!(matches!(Error::from(io_error()), Error::Io(_)));
+		assert!(matches!(
+			Error::from(InputError::Stdout),
+			Error::Input(InputError::Stdout)
+		));
+	}
+}
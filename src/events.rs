@@ -1,30 +1,171 @@
-use std::sync::Arc;
-
 use serenity::{async_trait, model::id::GuildId};
 use songbird::{
-	Event, EventContext, EventHandler as VoiceEventHandler, Songbird,
+	tracks::{create_player, Track, TrackHandle},
+	Event, EventContext, EventHandler as VoiceEventHandler,
+};
+use tracing::warn;
+
+use crate::{
+	error::Result,
+	idle::{Idle, IdleReason},
+	library::{resolver_for, LibraryKey},
+	resolver::{ResolverKey, TrackDescriptor},
+	settings::{self, PlaybackMode},
+	utils::{get_requester, is_dequeued, prefetch, Requester},
 };
-use tracing::error;
 
 pub(crate) struct TrackEnd {
 	pub guild_id: GuildId,
-	pub manager: Arc<Songbird>,
+	/// Also gives access to the client data, for the settings and resolvers.
+	pub idle: Idle,
+}
+
+impl TrackEnd {
+	/// Creates a new track playing the same thing as one that has ended, with
+	/// the same volume and requester.
+	async fn replay(
+		&self,
+		track: &TrackDescriptor,
+		requester: Option<Requester>,
+		volume: f32,
+	) -> Result<Track> {
+		let resolver = {
+			let data = self.idle.data.read().await;
+			resolver_for(
+				&track.url,
+				data.get::<ResolverKey>()
+					.expect("Resolver placed in at initialisation."),
+				data.get::<LibraryKey>(),
+			)
+		};
+
+		let (mut track, handle) = create_player(resolver.input(track).await?);
+		track.set_volume(volume);
+		if let Some(requester) = requester {
+			handle
+				.typemap()
+				.write()
+				.await
+				.insert::<Requester>(requester);
+		}
+
+		Ok(track)
+	}
+
+	/// Adds the tracks that have ended back to the end of the queue, except
+	/// for those removed from it on purpose.
+	async fn loop_queue(&self, ended: &[&TrackHandle], volume: f32) {
+		for handle in ended {
+			if is_dequeued(handle).await {
+				continue;
+			}
+
+			let track = match TrackDescriptor::from_metadata(handle.metadata())
+			{
+				Some(track) => track,
+				None => continue,
+			};
+
+			match self
+				.replay(&track, get_requester(handle).await, volume)
+				.await
+			{
+				Ok(track) => match self.idle.manager.get(self.guild_id) {
+					Some(handler_lock) => {
+						handler_lock.lock().await.enqueue(track)
+					}
+					None => return,
+				},
+				Err(e) => warn!("Could not loop <{}>: {:?}", track.url, e),
+			}
+		}
+	}
+
+	/// Queues a track related to the last one that ended.
+	async fn autoplay(&self, last: &TrackHandle, volume: f32) {
+		let track = match TrackDescriptor::from_metadata(last.metadata()) {
+			Some(track) => track,
+			None => return,
+		};
+
+		let resolver = {
+			let data = self.idle.data.read().await;
+			data.get::<ResolverKey>()
+				.expect("Resolver placed in at initialisation.")
+				.clone()
+		};
+
+		let related = match resolver.related(&track).await {
+			Ok(Some(related)) => related,
+			Ok(None) => return,
+			Err(e) => {
+				warn!(
+					"Could not find a track related to <{}>: {:?}",
+					track.url, e
+				);
+				return;
+			}
+		};
+
+		match self
+			.replay(&related, get_requester(last).await, volume)
+			.await
+		{
+			Ok(track) => {
+				if let Some(handler_lock) = self.idle.manager.get(self.guild_id)
+				{
+					let mut handler = handler_lock.lock().await;
+					// something may have been queued while searching
+					if handler.queue().is_empty() {
+						handler.enqueue(track);
+					}
+				}
+			}
+			Err(e) => warn!("Could not autoplay <{}>: {:?}", related.url, e),
+		}
+	}
 }
 
 #[async_trait]
 impl VoiceEventHandler for TrackEnd {
 	async fn act(&self, ctx: &EventContext<'_>) -> Option<Event> {
-		if let EventContext::Track(_) = ctx {
-			let handler_lock = self.manager.get(self.guild_id)?;
-			let queue_empty = handler_lock.lock().await.queue().is_empty();
+		if let EventContext::Track(ended) = ctx {
+			let ended =
+				ended.iter().map(|(_, handle)| *handle).collect::<Vec<_>>();
+			let settings = settings::get(&self.idle.data, self.guild_id).await;
+			let volume = settings.volume_multiplier();
 
-			if queue_empty {
-				if let Err(e) = self.manager.remove(self.guild_id).await {
-					error!(
-						"Could not leave {}'s voice channel: {:?}",
-						self.guild_id, e
-					);
+			// the bot has left, so there is nothing to continue
+			let handler_lock = self.idle.manager.get(self.guild_id)?;
+
+			match settings.mode {
+				PlaybackMode::Normal => {}
+				PlaybackMode::LoopQueue => {
+					self.loop_queue(&ended, volume).await
+				}
+				PlaybackMode::LoopTrack => {
+					if let Some(current) =
+						handler_lock.lock().await.queue().current()
+					{
+						let _ = current.enable_loop();
+					}
 				}
+				PlaybackMode::Autoplay => {
+					let queue_empty =
+						handler_lock.lock().await.queue().is_empty();
+					if let (true, Some(last)) = (queue_empty, ended.last()) {
+						self.autoplay(last, volume).await;
+					}
+				}
+			}
+
+			let queue_empty = {
+				let handler = handler_lock.lock().await;
+				prefetch(handler.queue());
+				handler.queue().is_empty()
+			};
+			if queue_empty {
+				self.idle.start(self.guild_id, IdleReason::QueueEmpty).await;
 			}
 		}
 
@@ -0,0 +1,220 @@
+use std::{
+	collections::HashMap,
+	sync::{
+		atomic::{AtomicU64, Ordering},
+		Arc,
+	},
+	time::Duration,
+};
+
+use serenity::{
+	cache::Cache,
+	client::Context,
+	model::id::{ChannelId, GuildId},
+	prelude::{Mutex, RwLock, TypeMap, TypeMapKey},
+};
+use songbird::{error::JoinError, Songbird};
+use tokio::task::JoinHandle;
+use tracing::{debug, warn};
+
+use crate::{
+	config, loads, settings, snapshot::QueueSnapshot, utils::get_listeners,
+};
+
+/// Why the bot would leave a voice channel.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub(crate) enum IdleReason {
+	/// Nothing is left in the queue.
+	QueueEmpty,
+	/// Nobody else is in the voice channel.
+	Alone,
+}
+
+impl IdleReason {
+	fn timeout(self) -> Duration {
+		match self {
+			Self::QueueEmpty => config::get().idle_timeout(),
+			Self::Alone => config::get().alone_timeout(),
+		}
+	}
+}
+
+type Timers = HashMap<(GuildId, IdleReason), (u64, JoinHandle<()>)>;
+
+/// Used to tell timers apart, so that a timer only removes itself.
+static NEXT_TIMER: AtomicU64 = AtomicU64::new(0);
+
+/// The running timers that will make the bot leave each guild.
+pub(crate) struct IdleKey;
+
+impl TypeMapKey for IdleKey {
+	type Value = Arc<Mutex<Timers>>;
+}
+
+/// What is needed to check on and leave a voice channel, which is available
+/// both from commands and voice events.
+#[derive(Clone)]
+pub(crate) struct Idle {
+	pub manager: Arc<Songbird>,
+	pub cache: Arc<Cache>,
+	pub data: Arc<RwLock<TypeMap>>,
+}
+
+impl Idle {
+	pub(crate) async fn from_context(ctx: &Context) -> Self {
+		Self {
+			manager: songbird::get(ctx)
+				.await
+				.expect("Songbird Voice Client placed in at initialisation.")
+				.clone(),
+			cache: ctx.cache.clone(),
+			data: ctx.data.clone(),
+		}
+	}
+
+	async fn timers(&self) -> Arc<Mutex<Timers>> {
+		self.data
+			.read()
+			.await
+			.get::<IdleKey>()
+			.expect("Idle timers placed in at initialisation.")
+			.clone()
+	}
+
+	/// Leaves the guild's voice channel once the timeout for the reason has
+	/// passed, restarting the timer if it is already running.
+	///
+	/// Nothing happens if the guild has 24/7 mode on, or if the reason no
+	/// longer applies when the time is up.
+	pub(crate) async fn start(&self, guild: GuildId, reason: IdleReason) {
+		if settings::get(&self.data, guild).await.always_on {
+			return;
+		}
+
+		let id = NEXT_TIMER.fetch_add(1, Ordering::Relaxed);
+		let idle = self.clone();
+		let timer = tokio::spawn(async move {
+			tokio::time::sleep(reason.timeout()).await;
+
+			{
+				let timers = idle.timers().await;
+				let mut timers = timers.lock().await;
+				if let Some((current, _)) = timers.get(&(guild, reason)) {
+					if *current == id {
+						timers.remove(&(guild, reason));
+					}
+				}
+			}
+
+			if idle.applies(guild, reason).await {
+				debug!("Leaving {} as it is idle: {:?}", guild, reason);
+				idle.leave(guild).await;
+			}
+		});
+
+		if let Some((_, previous)) = self
+			.timers()
+			.await
+			.lock()
+			.await
+			.insert((guild, reason), (id, timer))
+		{
+			previous.abort();
+		}
+	}
+
+	/// Starts a timer unless one is already running for the same reason.
+	pub(crate) async fn start_if_stopped(
+		&self,
+		guild: GuildId,
+		reason: IdleReason,
+	) {
+		let running = self
+			.timers()
+			.await
+			.lock()
+			.await
+			.contains_key(&(guild, reason));
+
+		if !running {
+			self.start(guild, reason).await;
+		}
+	}
+
+	pub(crate) async fn stop(&self, guild: GuildId, reason: IdleReason) {
+		if let Some((_, timer)) =
+			self.timers().await.lock().await.remove(&(guild, reason))
+		{
+			timer.abort();
+		}
+	}
+
+	/// Starts or stops the timer for the reason to match the current state of
+	/// the guild.
+	pub(crate) async fn check(&self, guild: GuildId, reason: IdleReason) {
+		if self.applies(guild, reason).await {
+			self.start_if_stopped(guild, reason).await;
+		} else {
+			self.stop(guild, reason).await;
+		}
+	}
+
+	/// Checks every reason to leave, for when the settings change.
+	pub(crate) async fn refresh(&self, guild: GuildId) {
+		for reason in [IdleReason::QueueEmpty, IdleReason::Alone] {
+			self.check(guild, reason).await;
+		}
+	}
+
+	/// Checks whether the bot should leave the guild for the reason.
+	async fn applies(&self, guild: GuildId, reason: IdleReason) -> bool {
+		if settings::get(&self.data, guild).await.always_on {
+			return false;
+		}
+
+		let handler_lock = match self.manager.get(guild) {
+			Some(handler_lock) => handler_lock,
+			None => return false,
+		};
+		let handler = handler_lock.lock().await;
+
+		match reason {
+			IdleReason::QueueEmpty => handler.queue().is_empty(),
+			IdleReason::Alone => match handler.current_channel() {
+				Some(channel) => {
+					drop(handler);
+					get_listeners(&self.cache, guild, ChannelId(channel.0))
+						.await
+						.is_empty()
+				}
+				None => false,
+			},
+		}
+	}
+
+	/// Leaves the guild's voice channel, stopping its timers.
+	pub(crate) async fn leave(&self, guild: GuildId) {
+		// loads still running would otherwise keep queueing into the call
+		loads::cancel(&self.data, guild, None, None).await;
+
+		loop {
+			match self.manager.remove(guild).await {
+				// the bot may have been disconnected already
+				Ok(()) | Err(JoinError::NoCall) => break,
+				Err(e) => {
+					warn!(
+						"Could not leave voice channel: {}, trying again in 5 \
+						 seconds",
+						e
+					);
+					tokio::time::sleep(Duration::from_secs(5)).await;
+				}
+			}
+		}
+		QueueSnapshot::discard(guild).await;
+
+		for reason in [IdleReason::QueueEmpty, IdleReason::Alone] {
+			self.stop(guild, reason).await;
+		}
+	}
+}
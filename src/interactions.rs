@@ -0,0 +1,1119 @@
+use std::{ops::Range, time::Duration};
+
+use serde_json::Value;
+use serenity::{
+	builder::{CreateApplicationCommandOption, CreateApplicationCommands},
+	client::Context,
+	framework::standard::CommandResult,
+	model::interactions::{
+		application_command::{
+			ApplicationCommand, ApplicationCommandInteraction,
+			ApplicationCommandInteractionDataOption,
+			ApplicationCommandOptionType,
+		},
+		autocomplete::AutocompleteInteraction,
+		Interaction,
+	},
+	model::{
+		guild::Member,
+		id::{RoleId, UserId},
+	},
+};
+use tracing::{error, info};
+
+use crate::{
+	commands::{
+		about::*,
+		cancel::*,
+		config::*,
+		dj::*,
+		helpers::{check_text_channel, CommandSource},
+		library::*,
+		mode::*,
+		nowplaying::*,
+		pause::*,
+		ping::*,
+		play::*,
+		playlist::*,
+		playnext::*,
+		queue::*,
+		remove::*,
+		reorder::*,
+		repeat::*,
+		restore::*,
+		resume::*,
+		search::*,
+		seek::*,
+		shuffle::*,
+		skip::*,
+		stay::*,
+		stop::*,
+		version::*,
+		volume::*,
+	},
+	settings::KEYS,
+	utils::{parse_duration, parse_positions, ObtainTitle},
+};
+
+/// Registers a slash command for each of the prefix commands.
+pub(crate) async fn register(ctx: &Context) {
+	match ApplicationCommand::set_global_application_commands(
+		&ctx.http,
+		create_commands,
+	)
+	.await
+	{
+		Ok(commands) => info!("Registered {} slash commands", commands.len()),
+		Err(e) => error!("Could not register slash commands: {:?}", e),
+	}
+}
+
+fn create_commands(
+	commands: &mut CreateApplicationCommands,
+) -> &mut CreateApplicationCommands {
+	use ApplicationCommandOptionType::*;
+
+	commands
+		.create_application_command(|c| {
+			c.name("about").description("Information about the bot")
+		})
+		.create_application_command(|c| {
+			c.name("cancel")
+				.description("Stops loading songs into the queue")
+				.create_option(|o| {
+					o.name("load")
+						.description(
+							"Number of the load, or every load you can cancel",
+						)
+						.kind(Integer)
+						.min_int_value(1)
+				})
+		})
+		.create_application_command(|c| {
+			c.name("config")
+				.description("Shows or changes the settings of the server")
+				.create_option(|o| {
+					o.name("get")
+						.description("Shows one setting, or all of them")
+						.kind(SubCommand)
+						.create_sub_option(|o| {
+							add_setting_choices(
+								o.name("setting")
+									.description("The setting to show")
+									.kind(String),
+							)
+						})
+				})
+				.create_option(|o| {
+					o.name("set")
+						.description("Changes a setting")
+						.kind(SubCommand)
+						.create_sub_option(|o| {
+							add_setting_choices(
+								o.name("setting")
+									.description("The setting to change")
+									.kind(String)
+									.required(true),
+							)
+						})
+						.create_sub_option(|o| {
+							o.name("value")
+								.description("The new value")
+								.kind(String)
+								.required(true)
+						})
+				})
+				.create_option(|o| {
+					o.name("reset")
+						.description("Puts a setting back to its default")
+						.kind(SubCommand)
+						.create_sub_option(|o| {
+							add_setting_choices(
+								o.name("setting")
+									.description("The setting to reset")
+									.kind(String)
+									.required(true),
+							)
+						})
+				})
+		})
+		.create_application_command(|c| {
+			c.name("dj")
+				.description("Shows or changes the role with full control")
+				.create_option(|o| {
+					o.name("role").description("The new DJ role").kind(Role)
+				})
+				.create_option(|o| {
+					o.name("clear")
+						.description("Remove the DJ role")
+						.kind(Boolean)
+				})
+		})
+		.create_application_command(|c| {
+			c.name("forward")
+				.description("Skips ahead in the currently playing song")
+				.create_option(|o| {
+					o.name("duration")
+						.description("How far to skip, such as 30 or 1m")
+						.kind(String)
+						.required(true)
+				})
+		})
+		.create_application_command(|c| {
+			c.name("library")
+				.description("Plays music from the bot's music library")
+				.create_option(|o| {
+					o.name("list")
+						.description("Lists the tracks in the library")
+						.kind(SubCommand)
+						.create_sub_option(|o| {
+							o.name("page")
+								.description("Page of the listing")
+								.kind(Integer)
+								.min_int_value(1)
+						})
+				})
+				.create_option(|o| {
+					o.name("search")
+						.description(
+							"Searches the library by title, artist, album and \
+							 file name",
+						)
+						.kind(SubCommand)
+						.create_sub_option(|o| {
+							o.name("query")
+								.description("Search terms")
+								.kind(String)
+								.required(true)
+						})
+				})
+				.create_option(|o| {
+					o.name("play")
+						.description(
+							"Plays a track or directory from the library",
+						)
+						.kind(SubCommand)
+						.create_sub_option(|o| {
+							o.name("query")
+								.description(
+									"Track number, path in the library, or \
+									 search terms",
+								)
+								.kind(String)
+								.required(true)
+						})
+				})
+		})
+		.create_application_command(|c| {
+			c.name("mode")
+				.description("Shows or changes what happens as songs end")
+				.create_option(|o| {
+					o.name("mode")
+						.description("The new playback mode")
+						.kind(String)
+						.add_string_choice("Normal", "normal")
+						.add_string_choice("Loop queue", "queue")
+						.add_string_choice("Loop track", "track")
+						.add_string_choice("Autoplay", "autoplay")
+				})
+		})
+		.create_application_command(|c| {
+			c.name("move")
+				.description(
+					"Moves the song at one position in the queue to another",
+				)
+				.create_option(|o| {
+					o.name("from")
+						.description("Position of the track to move")
+						.kind(Integer)
+						.min_int_value(1)
+						.required(true)
+						.set_autocomplete(true)
+				})
+				.create_option(|o| {
+					o.name("to")
+						.description("Position to move the track to")
+						.kind(Integer)
+						.min_int_value(1)
+						.required(true)
+						.set_autocomplete(true)
+				})
+		})
+		.create_application_command(|c| {
+			c.name("nowplaying")
+				.description(
+					"Shows the currently playing song and how far through it is",
+				)
+				.create_option(|o| {
+					o.name("live")
+						.description(
+							"Keep updating the message until the song ends, for up \
+							 to 14 minutes",
+						)
+						.kind(Boolean)
+				})
+		})
+		.create_application_command(|c| {
+			c.name("pause")
+				.description("Pauses the currently playing song")
+		})
+		.create_application_command(|c| {
+			c.name("ping")
+				.description("Causes the bot to reply with \"Pong!\"")
+		})
+		.create_application_command(|c| {
+			c.name("play")
+				.description(
+					"Downloads and plays the provided link, or searches for \
+					 the video on YouTube",
+				)
+				.create_option(|o| {
+					o.name("query")
+						.description("Link or search terms")
+						.kind(String)
+						.required(true)
+				})
+				.create_option(|o| {
+					o.name("single")
+						.description(
+							"Only play the linked video, not the playlist it is in",
+						)
+						.kind(Boolean)
+				})
+		})
+		.create_application_command(|c| {
+			c.name("playlist")
+				.description("Saves, loads and shares playlists")
+				.create_option(|o| {
+					o.name("list")
+						.description(
+							"Lists your playlists and the ones shared in the \
+							 server",
+						)
+						.kind(SubCommand)
+				})
+				.create_option(|o| {
+					add_playlist_name(
+						o.name("show")
+							.description("Shows the songs in a playlist")
+							.kind(SubCommand),
+					)
+				})
+				.create_option(|o| {
+					add_playlist_name(
+						o.name("save")
+							.description(
+								"Saves the queue as one of your playlists",
+							)
+							.kind(SubCommand),
+					)
+				})
+				.create_option(|o| {
+					add_playlist_name(
+						o.name("add")
+							.description(
+								"Adds links, or the current song, to one of \
+								 your playlists",
+							)
+							.kind(SubCommand),
+					)
+					.create_sub_option(|o| {
+						o.name("links")
+							.description("Links separated by spaces")
+							.kind(String)
+					})
+				})
+				.create_option(|o| {
+					add_playlist_name(
+						o.name("remove")
+							.description(
+								"Removes a song from one of your playlists",
+							)
+							.kind(SubCommand),
+					)
+					.create_sub_option(|o| {
+						o.name("position")
+							.description("Position of the song in the playlist")
+							.kind(Integer)
+							.min_int_value(1)
+							.required(true)
+					})
+				})
+				.create_option(|o| {
+					add_playlist_name(
+						o.name("delete")
+							.description("Deletes one of your playlists")
+							.kind(SubCommand),
+					)
+				})
+				.create_option(|o| {
+					add_playlist_name(
+						o.name("load")
+							.description(
+								"Adds the songs of a playlist to the queue",
+							)
+							.kind(SubCommand),
+					)
+				})
+				.create_option(|o| {
+					add_playlist_name(
+						o.name("share")
+							.description(
+								"Shares one of your playlists with the server",
+							)
+							.kind(SubCommand),
+					)
+				})
+				.create_option(|o| {
+					add_playlist_name(
+						o.name("unshare")
+							.description(
+								"Removes a playlist shared in the server",
+							)
+							.kind(SubCommand),
+					)
+				})
+				.create_option(|o| {
+					add_playlist_name(
+						o.name("export")
+							.description("Sends a playlist as a file")
+							.kind(SubCommand),
+					)
+					.create_sub_option(|o| {
+						o.name("format")
+							.description("JSON by default")
+							.kind(String)
+							.add_string_choice("json", "json")
+							.add_string_choice("links", "links")
+					})
+				})
+				.create_option(|o| {
+					add_playlist_name(
+						o.name("import")
+							.description(
+								"Creates one of your playlists from exported \
+								 JSON or links",
+							)
+							.kind(SubCommand),
+					)
+					.create_sub_option(|o| {
+						o.name("links")
+							.description(
+								"Exported JSON, or links separated by spaces",
+							)
+							.kind(String)
+							.required(true)
+					})
+				})
+		})
+		.create_application_command(|c| {
+			c.name("playnext")
+				.description(
+					"Plays the provided link or search after the current song",
+				)
+				.create_option(|o| {
+					o.name("query")
+						.description("Link or search terms")
+						.kind(String)
+						.required(true)
+				})
+		})
+		.create_application_command(|c| {
+			c.name("queue")
+				.description("Shows the current queue")
+				.create_option(|o| {
+					o.name("page")
+						.description("Page of the queue to show")
+						.kind(Integer)
+						.min_int_value(1)
+				})
+		})
+		.create_application_command(|c| {
+			c.name("remove")
+				.description(
+					"Removes tracks by position, by requester or by title, \
+					 leaving the current song",
+				)
+				.create_option(|o| {
+					o.name("positions")
+						.description("Positions and ranges, such as 3 5-7")
+						.kind(String)
+				})
+				.create_option(|o| {
+					o.name("user")
+						.description("Remove every track this user queued")
+						.kind(User)
+				})
+				.create_option(|o| {
+					o.name("match")
+						.description(
+							"Remove tracks with titles containing this",
+						)
+						.kind(String)
+				})
+		})
+		.create_application_command(|c| {
+			c.name("repeat")
+				.description(
+					"Repeat the selected track, or the current track, by the \
+					 specified number of times",
+				)
+				.create_option(|o| {
+					o.name("times")
+						.description("Number of times to repeat the track")
+						.kind(Integer)
+						.min_int_value(0)
+						.required(true)
+				})
+				.create_option(|o| {
+					o.name("position")
+						.description("Position of the track in the queue")
+						.kind(Integer)
+						.min_int_value(0)
+						.set_autocomplete(true)
+				})
+		})
+		.create_application_command(|c| {
+			c.name("restore").description(
+				"Restores the queue that was saved when the bot last shut down",
+			)
+		})
+		.create_application_command(|c| {
+			c.name("resume").description("Resumes the paused song")
+		})
+		.create_application_command(|c| {
+			c.name("rewind")
+				.description("Goes back in the currently playing song")
+				.create_option(|o| {
+					o.name("duration")
+						.description("How far to go back, such as 30 or 1m")
+						.kind(String)
+						.required(true)
+				})
+		})
+		.create_application_command(|c| {
+			c.name("search")
+				.description("Search for a video on YouTube")
+				.create_option(|o| {
+					o.name("query")
+						.description("Search terms")
+						.kind(String)
+						.required(true)
+				})
+		})
+		.create_application_command(|c| {
+			c.name("seek")
+				.description("Jumps to a point in the currently playing song")
+				.create_option(|o| {
+					o.name("timestamp")
+						.description("Such as 1:23, 1h2m3s or 83")
+						.kind(String)
+						.required(true)
+				})
+		})
+		.create_application_command(|c| {
+			c.name("shuffle").description("Shuffles the current queue")
+		})
+		.create_application_command(|c| {
+			c.name("skip")
+				.description(
+					"Skips the currently playing song, or removes the song at \
+					 the given position",
+				)
+				.create_option(|o| {
+					o.name("position")
+						.description("Position of the track in the queue")
+						.kind(Integer)
+						.min_int_value(0)
+						.set_autocomplete(true)
+				})
+		})
+		.create_application_command(|c| {
+			c.name("stay")
+				.description(
+					"Shows or changes whether the bot stays in the voice \
+					 channel when idle",
+				)
+				.create_option(|o| {
+					o.name("enabled")
+						.description("Whether to turn 24/7 mode on")
+						.kind(Boolean)
+				})
+		})
+		.create_application_command(|c| {
+			c.name("stop").description(
+				"Stops and disconnects the bot from the voice channel, \
+				 cancelling any loading songs",
+			)
+		})
+		.create_application_command(|c| {
+			c.name("swap")
+				.description("Swaps the songs at two positions in the queue")
+				.create_option(|o| {
+					o.name("first")
+						.description("Position of one track")
+						.kind(Integer)
+						.min_int_value(1)
+						.required(true)
+						.set_autocomplete(true)
+				})
+				.create_option(|o| {
+					o.name("second")
+						.description("Position of the other track")
+						.kind(Integer)
+						.min_int_value(1)
+						.required(true)
+						.set_autocomplete(true)
+				})
+		})
+		.create_application_command(|c| {
+			c.name("version")
+				.description("Get the build details of the bot")
+		})
+		.create_application_command(|c| {
+			c.name("volume")
+				.description("Shows or changes the volume of every song")
+				.create_option(|o| {
+					o.name("percent")
+						.description("New volume, from 0 to 200")
+						.kind(Integer)
+						.min_int_value(0)
+						.max_int_value(200)
+				})
+		})
+}
+
+pub(crate) async fn handle(ctx: &Context, interaction: Interaction) {
+	match interaction {
+		Interaction::ApplicationCommand(command) => {
+			if let Err(e) = run_command(ctx, &command).await {
+				error!(
+					"Error running slash command {}: {:?}",
+					command.data.name, e
+				);
+			}
+		}
+		Interaction::Autocomplete(autocomplete) => {
+			if let Err(e) = complete_position(ctx, &autocomplete).await {
+				error!(
+					"Error completing slash command {}: {:?}",
+					autocomplete.data.name, e
+				);
+			}
+		}
+		_ => {}
+	}
+}
+
+async fn run_command(
+	ctx: &Context,
+	command: &ApplicationCommandInteraction,
+) -> CommandResult {
+	command.defer(&ctx.http).await?;
+	let source = CommandSource::from(command);
+	let options = &command.data.options;
+
+	let guild_id = match command.guild_id {
+		Some(guild_id) => guild_id,
+		None => {
+			source
+				.say(ctx, "This command can only be used in servers.")
+				.await?;
+			return Ok(());
+		}
+	};
+
+	if let Err(reason) =
+		check_text_channel(ctx, guild_id, command.channel_id, command.user.id)
+			.await
+	{
+		source.say(ctx, reason).await?;
+		return Ok(());
+	}
+
+	match command.data.name.as_str() {
+		"about" => send_about(ctx, &source).await?,
+		"cancel" => {
+			cancel_loads(
+				ctx,
+				&source,
+				get_option(options, "load").and_then(Value::as_u64),
+			)
+			.await?
+		}
+		"config" => {
+			if can_manage_guild(command.member.as_ref()) {
+				run_config_command(ctx, &source, options).await?
+			} else {
+				source
+					.say(
+						ctx,
+						"Only members that can manage the server can do that.",
+					)
+					.await?
+			}
+		}
+		"dj" => run_dj(ctx, &source, options, command.member.as_ref()).await?,
+		"forward" => {
+			run_seek(ctx, &source, options, "duration", SeekTarget::Forward)
+				.await?
+		}
+		"library" => run_library_command(ctx, &source, options).await?,
+		"mode" => {
+			set_mode(
+				ctx,
+				&source,
+				get_string(options, "mode").and_then(|mode| mode.parse().ok()),
+			)
+			.await?
+		}
+		"move" => {
+			run_reorder(
+				ctx,
+				&source,
+				(get_index(options, "from"), get_index(options, "to")),
+				Reorder::Move,
+			)
+			.await?
+		}
+		"nowplaying" => {
+			show_now_playing(
+				ctx,
+				&source,
+				get_option(options, "live")
+					.and_then(Value::as_bool)
+					.unwrap_or(false),
+			)
+			.await?
+		}
+		"pause" => pause_track(ctx, &source).await?,
+		"ping" => send_pong(ctx, &source).await?,
+		"play" => {
+			play_query(
+				ctx,
+				&source,
+				get_string(options, "query").unwrap_or(""),
+				get_option(options, "single")
+					.and_then(Value::as_bool)
+					.unwrap_or(false),
+			)
+			.await?
+		}
+		"playlist" => run_playlist_command(ctx, &source, options).await?,
+		"playnext" => {
+			play_next(ctx, &source, get_string(options, "query").unwrap_or(""))
+				.await?
+		}
+		"queue" => {
+			show_queue(ctx, &source, get_index(options, "page").unwrap_or(1))
+				.await?
+		}
+		"remove" => match get_selection(options) {
+			Some(selection) => remove_tracks(ctx, &source, selection).await?,
+			None => {
+				source
+					.say(ctx, "Choose positions, a user, or text to match.")
+					.await?
+			}
+		},
+		"repeat" => {
+			repeat_track(
+				ctx,
+				&source,
+				get_index(options, "position"),
+				get_index(options, "times").unwrap_or(0),
+			)
+			.await?
+		}
+		"restore" => restore_queue(ctx, &source).await?,
+		"resume" => resume_track(ctx, &source).await?,
+		"rewind" => {
+			run_seek(ctx, &source, options, "duration", SeekTarget::Rewind)
+				.await?
+		}
+		"search" => {
+			search_query(
+				ctx,
+				&source,
+				get_string(options, "query").unwrap_or(""),
+			)
+			.await?
+		}
+		"seek" => {
+			run_seek(ctx, &source, options, "timestamp", SeekTarget::To).await?
+		}
+		"shuffle" => shuffle_queue(ctx, &source).await?,
+		"skip" => {
+			skip_track(ctx, &source, get_index(options, "position")).await?
+		}
+		"stay" => {
+			set_always_on(
+				ctx,
+				&source,
+				get_option(options, "enabled").and_then(Value::as_bool),
+			)
+			.await?
+		}
+		"stop" => stop_playing(ctx, &source).await?,
+		"swap" => {
+			run_reorder(
+				ctx,
+				&source,
+				(get_index(options, "first"), get_index(options, "second")),
+				Reorder::Swap,
+			)
+			.await?
+		}
+		"version" => send_version(ctx, &source).await?,
+		"volume" => {
+			set_volume(
+				ctx,
+				&source,
+				get_index(options, "percent").map(|volume| volume as u16),
+			)
+			.await?
+		}
+		name => error!("Received unknown slash command {}", name),
+	}
+
+	// a deferred response shows a loading indicator until it is filled in
+	if !source.has_responded() {
+		source.say(ctx, "Done.").await?;
+	}
+
+	Ok(())
+}
+
+async fn run_seek(
+	ctx: &Context,
+	source: &CommandSource<'_>,
+	options: &[ApplicationCommandInteractionDataOption],
+	name: &str,
+	target: fn(Duration) -> SeekTarget,
+) -> CommandResult {
+	match get_string(options, name).and_then(parse_duration) {
+		Some(duration) => seek_track(ctx, source, target(duration)).await,
+		None => {
+			source
+				.say(ctx, "Times must look like `1:23`, `1h2m3s` or `83`.")
+				.await?;
+			Ok(())
+		}
+	}
+}
+
+async fn run_dj(
+	ctx: &Context,
+	source: &CommandSource<'_>,
+	options: &[ApplicationCommandInteractionDataOption],
+	member: Option<&Member>,
+) -> CommandResult {
+	let allowed = can_manage_guild(member);
+
+	let role = if get_option(options, "clear")
+		.and_then(Value::as_bool)
+		.unwrap_or(false)
+	{
+		Some(None)
+	} else {
+		get_string(options, "role")
+			.and_then(|role| role.parse().ok())
+			.map(|role| Some(RoleId(role)))
+	};
+
+	if role.is_some() && !allowed {
+		source
+			.say(ctx, "Only members that can manage the server can do that.")
+			.await?;
+		return Ok(());
+	}
+
+	set_dj_role(ctx, source, role).await
+}
+
+fn add_setting_choices(
+	option: &mut CreateApplicationCommandOption,
+) -> &mut CreateApplicationCommandOption {
+	for key in KEYS {
+		option.add_string_choice(key, key);
+	}
+	option
+}
+
+fn add_playlist_name(
+	option: &mut CreateApplicationCommandOption,
+) -> &mut CreateApplicationCommandOption {
+	option.create_sub_option(|o| {
+		o.name("name")
+			.description("Name of the playlist")
+			.kind(ApplicationCommandOptionType::String)
+			.required(true)
+	})
+}
+
+/// Checks the permissions of the member using a slash command, as they can't
+/// be required like with prefix commands.
+fn can_manage_guild(member: Option<&Member>) -> bool {
+	member
+		.and_then(|member| member.permissions)
+		.map_or(false, |permissions| permissions.manage_guild())
+}
+
+async fn run_config_command(
+	ctx: &Context,
+	source: &CommandSource<'_>,
+	options: &[ApplicationCommandInteractionDataOption],
+) -> CommandResult {
+	let subcommand = match options.first() {
+		Some(subcommand) => subcommand,
+		None => return Ok(()),
+	};
+	let options = &subcommand.options;
+	let key = get_string(options, "setting").unwrap_or("");
+
+	match subcommand.name.as_str() {
+		"get" => show_config(ctx, source, get_string(options, "setting")).await,
+		"set" => {
+			set_config(
+				ctx,
+				source,
+				key,
+				get_string(options, "value").unwrap_or(""),
+			)
+			.await
+		}
+		"reset" => reset_config(ctx, source, key).await,
+		name => {
+			error!("Received unknown config subcommand {}", name);
+			Ok(())
+		}
+	}
+}
+
+async fn run_playlist_command(
+	ctx: &Context,
+	source: &CommandSource<'_>,
+	options: &[ApplicationCommandInteractionDataOption],
+) -> CommandResult {
+	let subcommand = match options.first() {
+		Some(subcommand) => subcommand,
+		None => return Ok(()),
+	};
+	let options = &subcommand.options;
+	let name = get_string(options, "name").unwrap_or("");
+	let links = get_string(options, "links").unwrap_or("");
+
+	match subcommand.name.as_str() {
+		"list" => list_playlists(ctx, source).await,
+		"show" => show_playlist(ctx, source, name).await,
+		"save" => save_playlist(ctx, source, name).await,
+		"add" => {
+			let links = links.split_whitespace().collect::<Vec<_>>();
+			add_to_playlist(ctx, source, name, &links).await
+		}
+		"remove" => {
+			remove_from_playlist(
+				ctx,
+				source,
+				name,
+				get_index(options, "position").unwrap_or(0),
+			)
+			.await
+		}
+		"delete" => delete_playlist(ctx, source, name).await,
+		"load" => load_playlist(ctx, source, name).await,
+		"share" => share_playlist(ctx, source, name, true).await,
+		"unshare" => share_playlist(ctx, source, name, false).await,
+		"export" => {
+			let format = match get_string(options, "format") {
+				Some("links") => ExportFormat::Links,
+				_ => ExportFormat::Json,
+			};
+			export_playlist(ctx, source, name, format).await
+		}
+		"import" => import_playlist(ctx, source, name, links).await,
+		name => {
+			error!("Received unknown playlist subcommand {}", name);
+			Ok(())
+		}
+	}
+}
+
+async fn run_reorder(
+	ctx: &Context,
+	source: &CommandSource<'_>,
+	positions: (Option<usize>, Option<usize>),
+	reorder: fn(usize, usize) -> Reorder,
+) -> CommandResult {
+	match positions {
+		(Some(first), Some(second)) => {
+			reorder_queue(ctx, source, reorder(first, second)).await
+		}
+		_ => Ok(()),
+	}
+}
+
+async fn run_library_command(
+	ctx: &Context,
+	source: &CommandSource<'_>,
+	options: &[ApplicationCommandInteractionDataOption],
+) -> CommandResult {
+	let subcommand = match options.first() {
+		Some(subcommand) => subcommand,
+		None => return Ok(()),
+	};
+	let options = &subcommand.options;
+
+	match subcommand.name.as_str() {
+		"list" => {
+			list_library(ctx, source, get_index(options, "page").unwrap_or(1))
+				.await
+		}
+		"search" => {
+			search_library(
+				ctx,
+				source,
+				get_string(options, "query").unwrap_or(""),
+			)
+			.await
+		}
+		"play" => {
+			play_from_library(
+				ctx,
+				source,
+				get_string(options, "query").unwrap_or(""),
+			)
+			.await
+		}
+		name => {
+			error!("Received unknown library subcommand {}", name);
+			Ok(())
+		}
+	}
+}
+
+/// Suggests queue positions starting with what has been typed, labelled with
+/// their track titles. The current track is left out for commands that can't
+/// move it.
+async fn complete_position(
+	ctx: &Context,
+	autocomplete: &AutocompleteInteraction,
+) -> CommandResult {
+	let manager = songbird::get(ctx)
+		.await
+		.expect("Songbird Voice Client placed in at initialisation.")
+		.clone();
+
+	let current_queue = match autocomplete
+		.guild_id
+		.and_then(|guild_id| manager.get(guild_id))
+	{
+		Some(handler_lock) => handler_lock.lock().await.queue().current_queue(),
+		None => Vec::new(),
+	};
+
+	let first = match autocomplete.data.name.as_str() {
+		"move" | "swap" => 1,
+		_ => 0,
+	};
+	let typed = autocomplete
+		.data
+		.options
+		.iter()
+		.find(|option| option.focused)
+		.and_then(|option| option.value.as_ref())
+		.map(|value| match value {
+			Value::String(text) => text.trim().to_string(),
+			value => value.to_string(),
+		})
+		.unwrap_or_default();
+
+	autocomplete
+		.create_autocomplete_response(&ctx.http, |response| {
+			for index in matching_positions(first..current_queue.len(), &typed)
+			{
+				let name =
+					format!("{} | {}", index, current_queue[index].get_title());
+				response.add_int_choice(
+					name.chars().take(100).collect::<String>(),
+					index as i64,
+				);
+			}
+			response
+		})
+		.await?;
+
+	Ok(())
+}
+
+/// The positions whose digits start with the typed text, as many as Discord
+/// shows.
+fn matching_positions(
+	positions: Range<usize>,
+	typed: &str,
+) -> impl Iterator<Item = usize> + '_ {
+	positions
+		.filter(move |position| position.to_string().starts_with(typed))
+		.take(25)
+}
+
+/// Reads the tracks chosen for the `remove` command, which is the first of
+/// its options that was given.
+fn get_selection(
+	options: &[ApplicationCommandInteractionDataOption],
+) -> Option<Selection> {
+	if let Some(positions) = get_string(options, "positions") {
+		parse_positions(positions).map(Selection::Positions)
+	} else if let Some(user) = get_string(options, "user") {
+		user.parse().ok().map(|user| Selection::User(UserId(user)))
+	} else {
+		get_string(options, "match")
+			.map(|text| Selection::Match(text.to_string()))
+	}
+}
+
+fn get_option<'a>(
+	options: &'a [ApplicationCommandInteractionDataOption],
+	name: &str,
+) -> Option<&'a Value> {
+	options
+		.iter()
+		.find(|option| option.name == name)
+		.and_then(|option| option.value.as_ref())
+}
+
+fn get_string<'a>(
+	options: &'a [ApplicationCommandInteractionDataOption],
+	name: &str,
+) -> Option<&'a str> {
+	get_option(options, name).and_then(Value::as_str)
+}
+
+fn get_index(
+	options: &[ApplicationCommandInteractionDataOption],
+	name: &str,
+) -> Option<usize> {
+	get_option(options, name)
+		.and_then(Value::as_u64)
+		.map(|index| index as usize)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::matching_positions;
+
+	#[test]
+	fn test_matching_positions() {
+		let all = matching_positions(1..200, "").collect::<Vec<_>>();
+		assert_eq!(all, (1..=25).collect::<Vec<_>>());
+
+		let typed = matching_positions(0..200, "15").collect::<Vec<_>>();
+		assert_eq!(
+			typed,
+			[15, 150, 151, 152, 153, 154, 155, 156, 157, 158, 159]
+		);
+
+		assert_eq!(matching_positions(1..10, "0").count(), 0);
+		assert_eq!(matching_positions(0..10, "x").count(), 0);
+	}
+}
@@ -0,0 +1,295 @@
+use std::{
+	path::{Component, Path, PathBuf},
+	process::Stdio,
+	sync::Arc,
+	time::Duration,
+};
+
+use futures_util::stream::{self, StreamExt};
+use once_cell::sync::OnceCell;
+use serde_json::Value;
+use serenity::{client::Context, prelude::TypeMapKey};
+use tokio::{fs, process::Command};
+use tracing::{info, warn};
+use url::Url;
+
+use crate::{
+	config::Config,
+	error::{Error, Result},
+	resolver::{list_audio_files, LocalResolver, Resolver, TrackDescriptor},
+};
+
+/// Number of files probed by ffprobe at the same time while indexing.
+const PROBE_CONCURRENCY: usize = 8;
+
+/// An audio file in the library.
+#[derive(Debug)]
+pub(crate) struct LibraryTrack {
+	/// Path of the file, relative to the library root.
+	pub path: PathBuf,
+	pub album: Option<String>,
+	pub descriptor: TrackDescriptor,
+}
+
+impl LibraryTrack {
+	/// The artist and title of the track, for listing.
+	pub(crate) fn name(&self) -> String {
+		let title = self
+			.descriptor
+			.title
+			.clone()
+			.unwrap_or_else(|| self.path.to_string_lossy().into_owned());
+
+		match self.descriptor.artist {
+			Some(ref artist) => format!("{} - {}", artist, title),
+			None => title,
+		}
+	}
+
+	/// Checks that every search term appears in the tags or path of the
+	/// track.
+	fn matches(&self, terms: &[String]) -> bool {
+		let fields = [
+			self.descriptor.title.as_deref(),
+			self.descriptor.artist.as_deref(),
+			self.album.as_deref(),
+			self.path.to_str(),
+		]
+		.iter()
+		.flatten()
+		.map(|field| field.to_lowercase())
+		.collect::<Vec<_>>();
+
+		terms
+			.iter()
+			.all(|term| fields.iter().any(|field| field.contains(term)))
+	}
+}
+
+/// A directory of audio files, indexed by their tags and file names.
+pub(crate) struct Library {
+	root: PathBuf,
+	resolver: Arc<LocalResolver>,
+	tracks: OnceCell<Vec<LibraryTrack>>,
+}
+
+impl Library {
+	pub(crate) fn new(root: PathBuf) -> Self {
+		Self {
+			resolver: Arc::new(LocalResolver::new(root.clone())),
+			root,
+			tracks: OnceCell::new(),
+		}
+	}
+
+	/// The resolver used to play tracks from the library.
+	pub(crate) fn resolver(&self) -> Arc<dyn Resolver> {
+		self.resolver.clone()
+	}
+
+	/// Reads the tags of every file in the library. Until this finishes, the
+	/// library appears empty.
+	pub(crate) async fn index(&self) -> Result<()> {
+		let root = self.root.canonicalize()?;
+		let files = list_audio_files(root.clone()).await?;
+
+		let tracks = stream::iter(files)
+			.map(|path| probe(&root, path))
+			.buffered(PROBE_CONCURRENCY)
+			.filter_map(|track| async move { track })
+			.collect::<Vec<_>>()
+			.await;
+
+		info!("Indexed {} tracks in the music library", tracks.len());
+		let _ = self.tracks.set(tracks);
+		Ok(())
+	}
+
+	/// Every track in the library, ordered by path.
+	pub(crate) fn tracks(&self) -> &[LibraryTrack] {
+		self.tracks.get().map(Vec::as_slice).unwrap_or_default()
+	}
+
+	/// Finds the tracks matching every word of the query, along with their
+	/// one-based position in [`tracks`](Self::tracks).
+	pub(crate) fn search(&self, query: &str) -> Vec<(usize, &LibraryTrack)> {
+		let terms = query
+			.split_whitespace()
+			.map(str::to_lowercase)
+			.collect::<Vec<_>>();
+
+		self.tracks()
+			.iter()
+			.enumerate()
+			.filter(|(_, track)| track.matches(&terms))
+			.map(|(index, track)| (index + 1, track))
+			.collect()
+	}
+
+	/// Finds the tracks to play for a query, which can be a position in the
+	/// library listing, a file or directory relative to the library root, or
+	/// search terms, in which case the best match is used.
+	pub(crate) async fn find(
+		&self,
+		query: &str,
+	) -> Result<Vec<TrackDescriptor>> {
+		if let Ok(position) = query.parse::<usize>() {
+			return position
+				.checked_sub(1)
+				.and_then(|index| self.tracks().get(index))
+				.map(|track| vec![track.descriptor.clone()])
+				.ok_or(Error::NoResults);
+		}
+
+		// paths leaving the root are refused before touching the file system,
+		// so that the reply doesn't tell whether they exist
+		let leaves_root = Path::new(query).components().any(|component| {
+			!matches!(component, Component::Normal(_) | Component::CurDir)
+		});
+		if leaves_root {
+			return Err(Error::OutsideLibrary);
+		}
+
+		if let Ok(path) = fs::canonicalize(self.root.join(query)).await {
+			// links inside the library can still lead out of it
+			if !path.starts_with(fs::canonicalize(&self.root).await?) {
+				return Err(Error::OutsideLibrary);
+			}
+
+			let url =
+				Url::from_file_path(path).map_err(|_| Error::OutsideLibrary)?;
+			let mut tracks = self.resolver.resolve(&url).await?;
+
+			// use the indexed tags where possible, as they are already known
+			for track in &mut tracks {
+				if let Some(indexed) = self
+					.tracks()
+					.iter()
+					.find(|indexed| indexed.descriptor.url == track.url)
+				{
+					*track = indexed.descriptor.clone();
+				}
+			}
+
+			return Ok(tracks);
+		}
+
+		self.search(query)
+			.first()
+			.map(|(_, track)| vec![track.descriptor.clone()])
+			.ok_or(Error::NoResults)
+	}
+}
+
+/// Reads the tags of a file using ffprobe, falling back to the file name when
+/// there are none.
+async fn probe(root: &Path, path: PathBuf) -> Option<LibraryTrack> {
+	let output = Command::new("ffprobe")
+		.args(["-v", "quiet", "-of", "json", "-show_format"])
+		.arg(&path)
+		.stdin(Stdio::null())
+		.output()
+		.await;
+
+	let format = match output {
+		Ok(output) => serde_json::from_slice::<Value>(&output.stdout)
+			.ok()
+			.and_then(|mut value| value.get_mut("format").map(Value::take)),
+		Err(e) => {
+			warn!("Could not run ffprobe on {:?}: {}", path, e);
+			None
+		}
+	}
+	.unwrap_or_default();
+
+	// tag names are capitalised differently between formats
+	let tag = |name: &str| {
+		format
+			.get("tags")
+			.and_then(Value::as_object)?
+			.iter()
+			.find(|(key, _)| key.eq_ignore_ascii_case(name))
+			.and_then(|(_, value)| value.as_str())
+			.map(str::to_string)
+	};
+
+	let url = Url::from_file_path(&path).ok()?;
+	Some(LibraryTrack {
+		album: tag("album"),
+		descriptor: TrackDescriptor {
+			title: tag("title").or_else(|| {
+				path.file_stem()
+					.map(|stem| stem.to_string_lossy().into_owned())
+			}),
+			artist: tag("artist"),
+			duration: format
+				.get("duration")
+				.and_then(Value::as_str)
+				.and_then(|duration| duration.parse::<f64>().ok())
+				.map(Duration::from_secs_f64),
+			..TrackDescriptor::from_url(url)
+		},
+		path: path.strip_prefix(root).ok()?.to_path_buf(),
+	})
+}
+
+/// Chooses the resolver able to play a URL, which is the library's own for
+/// files inside it.
+pub(crate) fn resolver_for(
+	url: &str,
+	resolver: &Arc<dyn Resolver>,
+	library: Option<&Arc<Library>>,
+) -> Arc<dyn Resolver> {
+	match library {
+		Some(library) if url.starts_with("file:") => library.resolver(),
+		_ => resolver.clone(),
+	}
+}
+
+pub(crate) struct LibraryKey;
+
+impl TypeMapKey for LibraryKey {
+	type Value = Arc<Library>;
+}
+
+/// Retrieves the music library, if one has been configured.
+pub(crate) async fn get(ctx: &Context) -> Option<Arc<Library>> {
+	ctx.data.read().await.get::<LibraryKey>().cloned()
+}
+
+/// Creates the library in the configured directory, if there is one.
+pub(crate) fn from_config(config: &Config) -> Option<Library> {
+	config.library_dir.clone().map(Library::new)
+}
+
+#[cfg(test)]
+mod tests {
+	use std::fs;
+
+	use super::Library;
+	use crate::error::Error;
+
+	#[tokio::test]
+	async fn test_find_rejects_outside_paths() {
+		let dir = std::env::temp_dir()
+			.join(format!("musicbot-library-{}", std::process::id()));
+		let root = dir.join("library");
+		fs::create_dir_all(&root).unwrap();
+		fs::write(root.join("inside.mp3"), b"").unwrap();
+		fs::write(dir.join("outside.mp3"), b"").unwrap();
+
+		let library = Library::new(root);
+		let inside = library.find("inside.mp3").await;
+		let outside = library.find("../outside.mp3").await;
+		let missing = library.find("../missing.mp3").await;
+		let absolute = library
+			.find(dir.join("outside.mp3").to_str().unwrap())
+			.await;
+		fs::remove_dir_all(&dir).unwrap();
+
+		assert_eq!(inside.unwrap().len(), 1);
+		assert!(matches!(outside, Err(Error::OutsideLibrary)));
+		assert!(matches!(missing, Err(Error::OutsideLibrary)));
+		assert!(matches!(absolute, Err(Error::OutsideLibrary)));
+	}
+}
@@ -0,0 +1,330 @@
+use std::{
+	collections::{BTreeMap, HashMap},
+	sync::{Arc, Mutex},
+	time::Duration,
+};
+
+use futures_util::{
+	future::{Fuse, Future, FutureExt},
+	stream::{self, Stream, StreamExt},
+};
+use serenity::{
+	client::Context,
+	model::{
+		channel::{Message, ReactionType},
+		id::{GuildId, UserId},
+	},
+	prelude::{RwLock, TypeMap, TypeMapKey},
+};
+use songbird::tracks::{Track, TrackHandle};
+use tokio::sync::{oneshot, watch};
+use tracing::warn;
+
+use crate::{
+	commands::helpers::{is_dj, CommandSource, StatusMessage},
+	error::{Error, Result},
+	settings,
+	utils::{queue_songs, EnqueueTrack, Progress},
+};
+
+/// How often the status message of a load is edited to show its progress.
+const PROGRESS_INTERVAL: Duration = Duration::from_secs(5);
+
+/// The loads running in a guild.
+#[derive(Default)]
+pub(crate) struct GuildLoads {
+	/// The id given to the next load. Ids are not reused, so that cancelling
+	/// an old id can't stop a newer load.
+	next_id: u64,
+	running: BTreeMap<u64, RunningLoad>,
+}
+
+struct RunningLoad {
+	user: UserId,
+	cancel: oneshot::Sender<()>,
+}
+
+/// The tracks still being loaded into each guild's queue. A blocking mutex is
+/// used so that a load can remove itself when it is dropped, and it is never
+/// held across an await.
+pub(crate) struct LoadsKey;
+
+impl TypeMapKey for LoadsKey {
+	type Value = Arc<Mutex<HashMap<GuildId, GuildLoads>>>;
+}
+
+async fn loads(
+	data: &RwLock<TypeMap>,
+) -> Arc<Mutex<HashMap<GuildId, GuildLoads>>> {
+	data.read()
+		.await
+		.get::<LoadsKey>()
+		.expect("Loads placed in at initialisation.")
+		.clone()
+}
+
+fn cancel_reaction() -> ReactionType {
+	ReactionType::Unicode("❌".to_string())
+}
+
+/// Tracks being loaded into the queue by a command, which can be cancelled
+/// until this is dropped.
+pub(crate) struct LoadJob {
+	id: u64,
+	guild: GuildId,
+	loads: Arc<Mutex<HashMap<GuildId, GuildLoads>>>,
+	/// Fused so that it can be waited on by more than one step of the load.
+	cancelled: Option<Fuse<oneshot::Receiver<()>>>,
+	/// Stops watching the status message for reactions once dropped.
+	_watching: Option<oneshot::Sender<()>>,
+}
+
+impl LoadJob {
+	/// Registers a load for the command, adding its id to the status message
+	/// and letting it be cancelled by reacting to the message.
+	pub(crate) async fn start(
+		ctx: &Context,
+		source: &CommandSource<'_>,
+		status: &mut StatusMessage<'_>,
+	) -> Self {
+		let guild = source.guild_id().unwrap();
+		let user = source.author().id;
+		let loads = loads(&ctx.data).await;
+		let (cancel, cancelled) = oneshot::channel();
+
+		let id = {
+			let mut loads = loads.lock().unwrap();
+			let guild_loads = loads.entry(guild).or_default();
+			guild_loads.next_id += 1;
+			guild_loads
+				.running
+				.insert(guild_loads.next_id, RunningLoad { user, cancel });
+			guild_loads.next_id
+		};
+
+		let mut job = Self {
+			id,
+			guild,
+			loads,
+			cancelled: Some(cancelled.fuse()),
+			_watching: None,
+		};
+
+		let content = format!(
+			"{} (load {}, react with {} to cancel)",
+			status.message().content,
+			id,
+			cancel_reaction()
+		);
+		// the load can still be cancelled with the command if the message
+		// can't be changed, so these failures don't stop it
+		if let Err(e) = status.edit_text(ctx, content).await {
+			warn!("Could not show load {} in {}: {:?}", id, guild, e);
+		}
+		if let Err(e) =
+			status.message().react(&ctx.http, cancel_reaction()).await
+		{
+			warn!("Could not add cancel reaction for load {}: {:?}", id, e);
+		}
+
+		let (watching, stopped) = oneshot::channel();
+		job._watching = Some(watching);
+		tokio::spawn(watch_reactions(
+			ctx.clone(),
+			status.message().clone(),
+			guild,
+			id,
+			stopped,
+		));
+
+		job
+	}
+
+	/// Queues the tracks for the command, editing the status message with the
+	/// progress every few seconds until they have all loaded or the load is
+	/// cancelled.
+	pub(crate) async fn queue(
+		&mut self,
+		ctx: &Context,
+		source: &CommandSource<'_>,
+		status: &mut StatusMessage<'_>,
+		handler: &impl EnqueueTrack,
+		song_stream: impl Stream<Item = Result<(Track, TrackHandle)>>,
+		total: Option<usize>,
+	) -> Result<String> {
+		let settings =
+			settings::get(&ctx.data, source.guild_id().unwrap()).await;
+		let (progress, mut updates) = watch::channel(Progress {
+			total,
+			..Progress::default()
+		});
+
+		let queueing = queue_songs(
+			handler,
+			self.wrap(song_stream),
+			source.requester(),
+			&settings,
+			Some(&progress),
+		);
+		tokio::pin!(queueing);
+
+		// the load's id and how to cancel it stay above the progress
+		let content = status.message().content.clone();
+		let reporting = async {
+			loop {
+				tokio::time::sleep(PROGRESS_INTERVAL).await;
+				if updates.changed().await.is_err() {
+					return;
+				}
+
+				let text = format!("{}\n{}", content, *updates.borrow());
+				let _ = status.edit_text(ctx, text).await;
+			}
+		};
+
+		tokio::select! {
+			result = &mut queueing => result,
+			_ = reporting => queueing.await,
+		}
+	}
+
+	/// Runs a step of the load before its tracks are queued, such as finding
+	/// them, giving [`Error::Cancelled`] instead if the load is cancelled first.
+	pub(crate) async fn run<T>(
+		&mut self,
+		step: impl Future<Output = Result<T>>,
+	) -> Result<T> {
+		let cancelled = self
+			.cancelled
+			.as_mut()
+			.expect("A load runs its steps before wrapping its stream");
+
+		tokio::select! {
+			biased;
+			Ok(()) = cancelled => Err(Error::Cancelled),
+			result = step => result,
+		}
+	}
+
+	/// Ends the stream of tracks once the load is cancelled, giving
+	/// [`Error::Cancelled`] as its last item. The track being resolved at the
+	/// time is dropped along with the rest of the stream.
+	fn wrap<T>(
+		&mut self,
+		song_stream: impl Stream<Item = Result<T>>,
+	) -> impl Stream<Item = Result<T>> {
+		let cancelled =
+			self.cancelled.take().expect("A load only wraps one stream");
+
+		stream::unfold(
+			Some((Box::pin(song_stream), cancelled)),
+			|state| async move {
+				let (mut song_stream, mut cancelled): (_, Fuse<_>) = state?;
+				tokio::select! {
+					biased;
+					Ok(()) = &mut cancelled => Some((Err(Error::Cancelled), None)),
+					song = song_stream.next() => {
+						song.map(|song| (song, Some((song_stream, cancelled))))
+					}
+				}
+			},
+		)
+	}
+}
+
+impl Drop for LoadJob {
+	fn drop(&mut self) {
+		if let Some(guild_loads) =
+			self.loads.lock().unwrap().get_mut(&self.guild)
+		{
+			guild_loads.running.remove(&self.id);
+		}
+	}
+}
+
+/// Cancels the load when someone allowed to reacts with the cancel emoji,
+/// removing the bot's reaction once the load is over.
+async fn watch_reactions(
+	ctx: Context,
+	message: Message,
+	guild: GuildId,
+	id: u64,
+	stopped: oneshot::Receiver<()>,
+) {
+	let bot = ctx.cache.current_user_id().await;
+
+	let watch = async {
+		loop {
+			let reaction = match message
+				.await_reaction(&ctx)
+				.filter(|reaction| reaction.emoji == cancel_reaction())
+				.await
+			{
+				Some(reaction) => reaction,
+				None => return,
+			};
+
+			let user = match reaction.as_inner_ref().user_id {
+				Some(user) if user != bot => user,
+				_ => continue,
+			};
+
+			let allowed = if is_dj(&ctx, guild, user).await {
+				None
+			} else {
+				Some(user)
+			};
+			if !cancel(&ctx.data, guild, Some(id), allowed).await.is_empty() {
+				return;
+			}
+		}
+	};
+
+	tokio::select! {
+		_ = watch => {}
+		_ = stopped => {}
+	}
+
+	let _ = ctx
+		.http
+		.delete_reaction(
+			message.channel_id.0,
+			message.id.0,
+			None,
+			&cancel_reaction(),
+		)
+		.await;
+}
+
+/// Cancels the guild's loads with the id, or all of them, returning the ids
+/// that were cancelled. Only loads started by `user` are cancelled if given.
+pub(crate) async fn cancel(
+	data: &RwLock<TypeMap>,
+	guild: GuildId,
+	id: Option<u64>,
+	user: Option<UserId>,
+) -> Vec<u64> {
+	let loads = loads(data).await;
+	let mut loads = loads.lock().unwrap();
+	let running = match loads.get_mut(&guild) {
+		Some(guild_loads) => &mut guild_loads.running,
+		None => return Vec::new(),
+	};
+
+	let ids = running
+		.iter()
+		.filter(|(load_id, load)| {
+			id.map_or(true, |id| id == **load_id)
+				&& user.map_or(true, |user| user == load.user)
+		})
+		.map(|(load_id, _)| *load_id)
+		.collect::<Vec<_>>();
+
+	for id in &ids {
+		if let Some(load) = running.remove(id) {
+			let _ = load.cancel.send(());
+		}
+	}
+
+	ids
+}
@@ -1,46 +1,131 @@
 mod commands;
+mod config;
+mod error;
 mod events;
+mod idle;
+mod interactions;
+mod library;
+mod loads;
+mod playlist;
+mod resolver;
+mod settings;
+mod snapshot;
+mod store;
 mod utils;
 
-use std::{collections::HashSet, env};
+use std::{collections::HashSet, process, sync::Arc, time::Duration};
 
-use once_cell::sync::Lazy;
 use serenity::{
 	async_trait,
-	framework::{standard::macros::group, StandardFramework},
+	framework::{
+		standard::{
+			macros::{check, group, hook},
+			Args, CommandOptions, DispatchError, Reason,
+		},
+		StandardFramework,
+	},
 	http::Http,
-	model::gateway::Ready,
+	model::{
+		channel::Message, gateway::Ready, id::GuildId,
+		interactions::Interaction, voice::VoiceState,
+	},
 	prelude::*,
 };
-use songbird::serenity::SerenityInit;
+use songbird::{serenity::SerenityInit, Songbird};
 use tracing::{error, info};
 use tracing_subscriber::{EnvFilter, FmtSubscriber};
 
+use commands::helpers::check_text_channel;
+use idle::{Idle, IdleReason};
+
 use commands::{
-	about::*, help::*, pause::*, ping::*, play::*, playnext::*, queue::*,
-	repeat::*, resume::*, search::*, shuffle::*, skip::*, stop::*, version::*,
+	about::*, cancel::*, config::*, dj::*, help::*, library::*, mode::*,
+	nowplaying::*, pause::*, ping::*, play::*, playlist::*, playnext::*,
+	queue::*, remove::*, reorder::*, repeat::*, restore::*, resume::*,
+	search::*, seek::*, shuffle::*, skip::*, stay::*, stop::*, version::*,
+	volume::*,
 };
 
-static QUEUE_CHUNK_SIZE: Lazy<usize> = Lazy::new(|| {
-	env::var("RUSTY_QUEUE_CHUNK_SIZE")
-		.ok()
-		.and_then(|size| size.parse().ok())
-		.unwrap_or(20)
-});
+#[hook]
+async fn guild_prefix(ctx: &Context, msg: &Message) -> Option<String> {
+	let prefix = match msg.guild_id {
+		Some(guild) => settings::get(&ctx.data, guild).await.prefix,
+		None => None,
+	};
+
+	Some(prefix.unwrap_or_else(|| config::get().prefix.clone()))
+}
+
+// keeps commands to the text channels the guild has bound the bot to
+#[check]
+#[name = "bound_channel"]
+#[display_in_help(false)]
+#[check_in_help(false)]
+async fn bound_channel_check(
+	ctx: &Context,
+	msg: &Message,
+	_: &mut Args,
+	_: &CommandOptions,
+) -> Result<(), Reason> {
+	match msg.guild_id {
+		Some(guild) => {
+			check_text_channel(ctx, guild, msg.channel_id, msg.author.id)
+				.await
+				.map_err(Reason::User)
+		}
+		None => Ok(()),
+	}
+}
+
+#[hook]
+async fn dispatch_error(ctx: &Context, msg: &Message, error: DispatchError) {
+	let reply = match error {
+		DispatchError::LackingPermissions(_) => {
+			"You don't have permission to use this command.".to_string()
+		}
+		DispatchError::CheckFailed(_, Reason::User(reason)) => reason,
+		_ => return,
+	};
+
+	let _ = msg.channel_id.say(&ctx.http, reply).await;
+}
 
 struct Handler;
 
 #[async_trait]
 impl EventHandler for Handler {
-	async fn ready(&self, _: Context, ready: Ready) {
+	async fn ready(&self, ctx: Context, ready: Ready) {
 		info!("Connected as : {}", ready.user.name);
+		interactions::register(&ctx).await;
+	}
+
+	async fn interaction_create(&self, ctx: Context, interaction: Interaction) {
+		interactions::handle(&ctx, interaction).await;
+	}
+
+	async fn voice_state_update(
+		&self,
+		ctx: Context,
+		guild: Option<GuildId>,
+		_old: Option<VoiceState>,
+		new: VoiceState,
+	) {
+		if let Some(guild) = guild.or(new.guild_id) {
+			Idle::from_context(&ctx)
+				.await
+				.check(guild, IdleReason::Alone)
+				.await;
+		}
 	}
 }
 
 #[group]
+#[checks(bound_channel)]
 #[commands(
-	about, pause, ping, play, playnext, queue, repeat, resume, search, shuffle,
-	skip, stop, version
+	about, cancel, config, dj, forward, library, mode, move_track, nowplaying,
+	pause, playlist, ping, play, playnext, queue, remove, repeat, restore,
+	resume, rewind, search, seek, shuffle, skip, stay, stop, swap, version,
+	volume
 )]
 struct General;
 
@@ -55,8 +140,16 @@ async fn main() {
 	tracing::subscriber::set_global_default(subscriber)
 		.expect("Failed to start logger.");
 
-	let token =
-		env::var("DISCORD_TOKEN").expect("Token environment variable not set");
+	let config = match config::Config::load() {
+		Ok(config) => config,
+		Err(e) => {
+			error!("Invalid configuration: {}", e);
+			process::exit(1);
+		}
+	};
+	config::set(config.clone());
+
+	let token = config.token.clone();
 	let http = Http::new_with_token(&token);
 
 	let (owners, bot_id) = match http.get_current_application_info().await {
@@ -71,29 +164,103 @@ async fn main() {
 
 	let framework = StandardFramework::new()
 		.configure(|c| {
-			if let Ok(prefix) = env::var("MUSICBOT_PREFIX") {
-				c.prefix(&prefix);
-			}
-
-			c.owners(owners)
+			// prefixes can differ between guilds, so they all come from the
+			// dynamic prefix
+			c.prefix("")
+				.dynamic_prefix(guild_prefix)
+				.owners(owners)
 				.on_mention(Some(bot_id))
 				.case_insensitivity(true)
 		})
+		.on_dispatch_error(dispatch_error)
 		.group(&GENERAL_GROUP)
 		.help(&HELP);
 
+	let songbird = Songbird::serenity();
 	let mut client = Client::builder(&token)
+		.application_id(bot_id.0)
 		.framework(framework)
 		.event_handler(Handler)
-		.register_songbird()
+		.register_songbird_with(songbird.clone())
 		.await
 		.expect("Error creating client");
 
+	client
+		.data
+		.write()
+		.await
+		.insert::<resolver::ResolverKey>(resolver::from_config(&config));
+	client
+		.data
+		.write()
+		.await
+		.insert::<settings::SettingsKey>(Default::default());
+	client
+		.data
+		.write()
+		.await
+		.insert::<idle::IdleKey>(Default::default());
+	client
+		.data
+		.write()
+		.await
+		.insert::<loads::LoadsKey>(Default::default());
+	client
+		.data
+		.write()
+		.await
+		.insert::<SkipVotes>(Default::default());
+
+	if let Some(library) = library::from_config(&config).map(Arc::new) {
+		client
+			.data
+			.write()
+			.await
+			.insert::<library::LibraryKey>(library.clone());
+
+		tokio::spawn(async move {
+			if let Err(e) = library.index().await {
+				error!("Could not index the music library: {:?}", e);
+			}
+		});
+	}
+
+	if let Err(e) = snapshot::find_pending().await {
+		error!("Could not find the queues saved before restarting: {}", e);
+	}
+
+	let cache = client.cache_and_http.cache.clone();
+	let manager = songbird.clone();
+	let snapshot_interval = Duration::from_secs(config.snapshot_interval);
+	tokio::spawn(async move {
+		let mut interval = tokio::time::interval(snapshot_interval);
+		loop {
+			interval.tick().await;
+			snapshot::save_all(&cache, &manager).await;
+		}
+	});
+
+	#[cfg(unix)]
+	tokio::spawn(async {
+		use tokio::signal::unix::{signal, SignalKind};
+
+		let mut hangup = signal(SignalKind::hangup())
+			.expect("Could not register SIGHUP handler");
+		while hangup.recv().await.is_some() {
+			match config::reload() {
+				Ok(()) => info!("Reloaded the configuration"),
+				Err(e) => error!("Kept the old configuration: {}", e),
+			}
+		}
+	});
+
 	let shard_manager = client.shard_manager.clone();
+	let cache = client.cache_and_http.cache.clone();
 	tokio::spawn(async move {
 		tokio::signal::ctrl_c()
 			.await
 			.expect("Could not register ctrl+c handler");
+		snapshot::save_all(&cache, &songbird).await;
 		shard_manager.lock().await.shutdown_all().await;
 	});
 
@@ -0,0 +1,387 @@
+use std::{collections::BTreeMap, io, sync::Arc, time::Duration};
+
+use async_stream::stream;
+use futures_core::Stream;
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use serenity::model::id::{GuildId, UserId};
+use songbird::tracks::{Track, TrackHandle};
+use tokio::sync::Mutex;
+use url::Url;
+
+use crate::{
+	error::{self, Error},
+	library::{resolver_for, Library},
+	resolver::{Resolver, TrackDescriptor},
+	utils::PlayParameter,
+};
+
+/// The most tracks a playlist can hold.
+pub(crate) const MAX_TRACKS: usize = 500;
+
+/// The most playlists each user or guild can have.
+pub(crate) const MAX_PLAYLISTS: usize = 50;
+
+/// The longest name a playlist can have.
+pub(crate) const MAX_NAME_LENGTH: usize = 32;
+
+/// Keeps changes to the same file from overwriting each other.
+static UPDATE_LOCK: Lazy<Mutex<()>> = Lazy::new(Default::default);
+
+/// A track in a playlist, with enough information to list it without
+/// resolving it.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub(crate) struct PlaylistEntry {
+	pub url: String,
+	#[serde(default)]
+	pub title: Option<String>,
+	#[serde(default)]
+	pub duration: Option<Duration>,
+	#[serde(default)]
+	pub thumbnail: Option<String>,
+}
+
+impl PlaylistEntry {
+	pub(crate) fn from_handle(handle: &TrackHandle) -> Option<Self> {
+		let metadata = handle.metadata();
+
+		Some(Self {
+			url: metadata.source_url.clone()?,
+			title: metadata.title.clone(),
+			duration: metadata.duration,
+			thumbnail: metadata.thumbnail.clone(),
+		})
+	}
+
+	/// What is known about the track, so that tracks with a title can be
+	/// queued without looking them up.
+	fn to_descriptor(&self) -> TrackDescriptor {
+		TrackDescriptor {
+			title: self.title.clone(),
+			duration: self.duration,
+			thumbnail: self.thumbnail.clone(),
+			..TrackDescriptor::from_url(self.url.clone())
+		}
+	}
+
+	/// Creates an entry from a link typed by a user, which is only checked
+	/// to be a URL as it will be resolved when the playlist is loaded.
+	pub(crate) fn from_link(link: &str) -> Result<Self, Error> {
+		match Url::parse(link) {
+			Ok(url) => Ok(Self {
+				url: url.into(),
+				title: None,
+				duration: None,
+				thumbnail: None,
+			}),
+			Err(_) => Err(Error::InvalidLink(link.to_string())),
+		}
+	}
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub(crate) struct Playlist {
+	pub tracks: Vec<PlaylistEntry>,
+	/// Who shared the playlist, for the playlists of a guild.
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub shared_by: Option<UserId>,
+}
+
+/// The format of an exported playlist, also accepted when importing.
+#[derive(Serialize, Deserialize)]
+struct Exported {
+	name: String,
+	tracks: Vec<PlaylistEntry>,
+}
+
+impl Playlist {
+	/// Creates a playlist from the tracks of a queue that can be played again.
+	pub(crate) fn capture(queue: &[TrackHandle]) -> Self {
+		Self {
+			tracks: queue
+				.iter()
+				.filter_map(PlaylistEntry::from_handle)
+				.collect(),
+			shared_by: None,
+		}
+	}
+
+	/// Reads a playlist exported as JSON, or a list of links separated by
+	/// whitespace, where lines starting with `#` are ignored.
+	pub(crate) fn import(contents: &str) -> Result<Self, Error> {
+		let contents = contents.trim();
+		let tracks = if contents.starts_with('{') {
+			serde_json::from_str::<Exported>(contents)
+				.map_err(|_| Error::InvalidPlaylistFile)?
+				.tracks
+		} else if contents.starts_with('[') {
+			serde_json::from_str(contents)
+				.map_err(|_| Error::InvalidPlaylistFile)?
+		} else {
+			contents
+				.lines()
+				.filter(|line| !line.trim_start().starts_with('#'))
+				.flat_map(str::split_whitespace)
+				.map(PlaylistEntry::from_link)
+				.collect::<Result<_, _>>()?
+		};
+
+		if tracks.is_empty() {
+			return Err(Error::EmptyPlaylist);
+		} else if tracks.len() > MAX_TRACKS {
+			return Err(Error::PlaylistTooLong);
+		}
+
+		Ok(Self {
+			tracks,
+			shared_by: None,
+		})
+	}
+
+	pub(crate) fn export_json(&self, name: &str) -> String {
+		serde_json::to_string_pretty(&Exported {
+			name: name.to_string(),
+			tracks: self.tracks.clone(),
+		})
+		.expect("Playlists can always be serialised.")
+	}
+
+	/// Lists the links of the playlist, one on each line.
+	pub(crate) fn export_links(&self) -> String {
+		self.tracks
+			.iter()
+			.map(|track| format!("{}\n", track.url))
+			.collect()
+	}
+
+	/// Resolves every track of the playlist in order. Tracks from the music
+	/// library are played from it.
+	pub(crate) fn get_tracks(
+		self,
+		resolver: Arc<dyn Resolver>,
+		library: Option<Arc<Library>>,
+	) -> impl Stream<Item = error::Result<(Track, TrackHandle)>> {
+		stream! {
+			for entry in self.tracks {
+				let resolver =
+					resolver_for(&entry.url, &resolver, library.as_ref());
+
+				let track = entry.to_descriptor();
+				for await result in
+					PlayParameter::Tracks(vec![track]).get_tracks(resolver)
+				{
+					yield result;
+				}
+			}
+		}
+	}
+}
+
+/// Who a set of playlists belongs to.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Owner {
+	User(UserId),
+	/// Playlists shared with everyone in a guild.
+	Guild(GuildId),
+}
+
+impl Owner {
+	fn path(self) -> String {
+		match self {
+			Self::User(user) => format!("playlists/users/{}.json", user),
+			Self::Guild(guild) => format!("playlists/guilds/{}.json", guild),
+		}
+	}
+}
+
+/// Every playlist of a user or guild, by name.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub(crate) struct Playlists {
+	pub playlists: BTreeMap<String, Playlist>,
+}
+
+impl Playlists {
+	pub(crate) async fn load(owner: Owner) -> io::Result<Self> {
+		crate::store::load(owner.path())
+			.await
+			.map(Option::unwrap_or_default)
+	}
+
+	/// Changes the playlists of a user or guild and saves them, unless the
+	/// change fails.
+	pub(crate) async fn update<T>(
+		owner: Owner,
+		change: impl FnOnce(&mut Self) -> Result<T, Error>,
+	) -> Result<T, Error> {
+		let _lock = UPDATE_LOCK.lock().await;
+
+		let mut playlists = Self::load(owner).await?;
+		let result = change(&mut playlists)?;
+		crate::store::save(owner.path(), &playlists).await?;
+
+		Ok(result)
+	}
+
+	pub(crate) fn get(&self, name: &str) -> Result<&Playlist, Error> {
+		self.playlists
+			.get(&check_name(name)?)
+			.ok_or_else(|| Error::PlaylistNotFound(name.to_string()))
+	}
+
+	pub(crate) fn get_mut(
+		&mut self,
+		name: &str,
+	) -> Result<&mut Playlist, Error> {
+		self.playlists
+			.get_mut(&check_name(name)?)
+			.ok_or_else(|| Error::PlaylistNotFound(name.to_string()))
+	}
+
+	/// Adds or replaces a playlist, returning whether it replaced another.
+	pub(crate) fn insert(
+		&mut self,
+		name: &str,
+		playlist: Playlist,
+	) -> Result<bool, Error> {
+		let name = check_name(name)?;
+		if playlist.tracks.len() > MAX_TRACKS {
+			return Err(Error::PlaylistTooLong);
+		}
+		if !self.playlists.contains_key(&name)
+			&& self.playlists.len() >= MAX_PLAYLISTS
+		{
+			return Err(Error::TooManyPlaylists);
+		}
+
+		Ok(self.playlists.insert(name, playlist).is_some())
+	}
+
+	/// Adds tracks to the end of a playlist, creating it if needed.
+	pub(crate) fn append(
+		&mut self,
+		name: &str,
+		tracks: Vec<PlaylistEntry>,
+	) -> Result<usize, Error> {
+		if let Err(Error::PlaylistNotFound(_)) = self.get(name) {
+			self.insert(name, Playlist::default())?;
+		}
+
+		let playlist = self.get_mut(name)?;
+		if playlist.tracks.len() + tracks.len() > MAX_TRACKS {
+			return Err(Error::PlaylistTooLong);
+		}
+		playlist.tracks.extend(tracks);
+
+		Ok(playlist.tracks.len())
+	}
+
+	pub(crate) fn remove(&mut self, name: &str) -> Result<Playlist, Error> {
+		self.playlists
+			.remove(&check_name(name)?)
+			.ok_or_else(|| Error::PlaylistNotFound(name.to_string()))
+	}
+}
+
+/// Finds a playlist of a user, or one shared in the guild if they have none
+/// by that name.
+pub(crate) async fn find(
+	user: UserId,
+	guild: GuildId,
+	name: &str,
+) -> Result<Playlist, Error> {
+	for owner in [Owner::User(user), Owner::Guild(guild)] {
+		match Playlists::load(owner).await?.get(name) {
+			Ok(playlist) => return Ok(playlist.clone()),
+			Err(Error::PlaylistNotFound(_)) => continue,
+			Err(e) => return Err(e),
+		}
+	}
+
+	Err(Error::PlaylistNotFound(name.to_string()))
+}
+
+/// Playlist names are kept in lowercase, so that they can be typed in any
+/// case.
+fn check_name(name: &str) -> Result<String, Error> {
+	let valid = |c: char| c.is_alphanumeric() || c == '-' || c == '_';
+	if name.is_empty()
+		|| name.chars().count() > MAX_NAME_LENGTH
+		|| !name.chars().all(valid)
+	{
+		return Err(Error::InvalidPlaylistName);
+	}
+
+	Ok(name.to_lowercase())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{Playlist, PlaylistEntry, Playlists};
+	use crate::error::Error;
+
+	fn entry(url: &str) -> PlaylistEntry {
+		PlaylistEntry::from_link(url).unwrap()
+	}
+
+	#[test]
+	fn test_import() {
+		let links = Playlist::import(
+			"# my songs\nhttps://example.com/a\n\nhttps://example.com/b \
+			 https://example.com/c",
+		)
+		.unwrap();
+		assert_eq!(
+			links.tracks,
+			[
+				entry("https://example.com/a"),
+				entry("https://example.com/b"),
+				entry("https://example.com/c"),
+			]
+		);
+
+		let json = Playlist::import(&links.export_json("mine")).unwrap();
+		assert_eq!(json.tracks, links.tracks);
+		let links_again = Playlist::import(&json.export_links()).unwrap();
+		assert_eq!(links_again.tracks, links.tracks);
+
+		assert!(matches!(
+			Playlist::import("https://example.com/a not-a-link"),
+			Err(Error::InvalidLink(link)) if link == "not-a-link"
+		));
+		assert!(matches!(
+			Playlist::import("{}"),
+			Err(Error::InvalidPlaylistFile)
+		));
+		assert!(matches!(
+			Playlist::import("# nothing"),
+			Err(Error::EmptyPlaylist)
+		));
+	}
+
+	#[test]
+	fn test_names() {
+		let mut playlists = Playlists::default();
+		assert!(!playlists.insert("Chill", Playlist::default()).unwrap());
+		assert!(playlists.insert("chill", Playlist::default()).unwrap());
+		assert_eq!(
+			playlists
+				.append("CHILL", vec![entry("https://example.com/a")])
+				.unwrap(),
+			1
+		);
+		assert_eq!(playlists.get("Chill").unwrap().tracks.len(), 1);
+
+		assert!(matches!(
+			playlists.insert("two words", Playlist::default()),
+			Err(Error::InvalidPlaylistName)
+		));
+		assert!(matches!(
+			playlists.get("@everyone"),
+			Err(Error::InvalidPlaylistName)
+		));
+		assert!(matches!(
+			playlists.remove("other"),
+			Err(Error::PlaylistNotFound(_))
+		));
+	}
+}
@@ -0,0 +1,78 @@
+use std::collections::{HashMap, HashSet};
+
+use serenity::async_trait;
+use songbird::input::{Codec, Container, Input, Reader};
+use url::Url;
+
+use super::{Resolver, TrackDescriptor};
+use crate::error::{Error, Result};
+
+/// A resolver that never touches the network, producing a moment of silence
+/// for every track.
+#[derive(Default)]
+pub(crate) struct FakeResolver {
+	playlists: HashMap<String, Vec<TrackDescriptor>>,
+	failures: HashSet<String>,
+}
+
+impl FakeResolver {
+	/// Makes the URL resolve to the given tracks.
+	#[cfg(test)]
+	pub(crate) fn with_playlist(
+		mut self,
+		url: impl Into<String>,
+		tracks: Vec<TrackDescriptor>,
+	) -> Self {
+		self.playlists.insert(url.into(), tracks);
+		self
+	}
+
+	/// Makes creating the audio of a track with the given URL fail.
+	#[cfg(test)]
+	pub(crate) fn with_failure(mut self, url: impl Into<String>) -> Self {
+		self.failures.insert(url.into());
+		self
+	}
+}
+
+#[async_trait]
+impl Resolver for FakeResolver {
+	async fn resolve(&self, url: &Url) -> Result<Vec<TrackDescriptor>> {
+		Ok(self
+			.playlists
+			.get(url.as_str())
+			.cloned()
+			.unwrap_or_else(|| vec![TrackDescriptor::from_url(url.as_str())]))
+	}
+
+	async fn search(
+		&self,
+		query: &str,
+		limit: usize,
+	) -> Result<Vec<TrackDescriptor>> {
+		Ok((1..=limit)
+			.map(|index| TrackDescriptor {
+				title: Some(format!("{} #{}", query, index)),
+				..TrackDescriptor::from_url(format!(
+					"fake://search/{}/{}",
+					query, index
+				))
+			})
+			.collect())
+	}
+
+	async fn input(&self, track: &TrackDescriptor) -> Result<Input> {
+		if self.failures.contains(&track.url) {
+			return Err(Error::Unavailable);
+		}
+
+		// one second of stereo 48kHz silence
+		Ok(Input::new(
+			true,
+			Reader::from_memory(vec![0; 48_000 * 2 * 4]),
+			Codec::FloatPcm,
+			Container::Raw,
+			Some(track.to_metadata()),
+		))
+	}
+}
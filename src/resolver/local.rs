@@ -0,0 +1,184 @@
+use std::{
+	fs, io,
+	path::{Path, PathBuf},
+};
+
+use serenity::async_trait;
+use songbird::input::{Input, Restartable};
+use tokio::task;
+use url::Url;
+
+use super::{Resolver, TrackDescriptor};
+use crate::error::{Error, Result};
+
+const AUDIO_EXTENSIONS: [&str; 8] =
+	["aac", "flac", "m4a", "mp3", "ogg", "opus", "wav", "webm"];
+
+/// Resolves `file://` URLs to audio files inside a root directory.
+pub(crate) struct LocalResolver {
+	root: PathBuf,
+}
+
+impl LocalResolver {
+	pub(crate) fn new(root: PathBuf) -> Self {
+		Self { root }
+	}
+
+	/// Converts a URL to a path, rejecting anything outside the root directory.
+	fn to_path(&self, url: &Url) -> Result<PathBuf> {
+		let path = url
+			.to_file_path()
+			.map_err(|_| invalid_input("not a file URL"))?
+			.canonicalize()?;
+
+		if path.starts_with(self.root.canonicalize()?) {
+			Ok(path)
+		} else {
+			Err(Error::OutsideLibrary)
+		}
+	}
+
+	fn descriptor(path: &Path) -> Option<TrackDescriptor> {
+		Some(TrackDescriptor {
+			title: path
+				.file_stem()
+				.map(|stem| stem.to_string_lossy().into_owned()),
+			..TrackDescriptor::from_url(Url::from_file_path(path).ok()?)
+		})
+	}
+}
+
+#[async_trait]
+impl Resolver for LocalResolver {
+	async fn resolve(&self, url: &Url) -> Result<Vec<TrackDescriptor>> {
+		let files = list_audio_files(self.to_path(url)?).await?;
+
+		Ok(files
+			.iter()
+			.filter_map(|path| Self::descriptor(path))
+			.collect())
+	}
+
+	async fn search(
+		&self,
+		query: &str,
+		limit: usize,
+	) -> Result<Vec<TrackDescriptor>> {
+		let files = list_audio_files(self.root.clone()).await?;
+		let query = query.to_lowercase();
+
+		Ok(files
+			.iter()
+			.filter(|path| {
+				path.file_name()
+					.map(|name| name.to_string_lossy().to_lowercase())
+					.map(|name| name.contains(&query))
+					.unwrap_or(false)
+			})
+			.filter_map(|path| Self::descriptor(path))
+			.take(limit)
+			.collect())
+	}
+
+	async fn input(&self, track: &TrackDescriptor) -> Result<Input> {
+		let url = Url::parse(&track.url)
+			.map_err(|_| invalid_input("not a file URL"))?;
+		let path = self.to_path(&url)?;
+		let mut input: Input =
+			Restartable::ffmpeg(path.clone(), true).await?.into();
+
+		// files without tags still need a name and a way to be found again
+		let metadata = &mut input.metadata;
+		if metadata.title.is_none() {
+			metadata.title = track
+				.title
+				.clone()
+				.or_else(|| metadata.track.clone())
+				.or_else(|| Self::descriptor(&path)?.title);
+		}
+		if metadata.artist.is_none() {
+			metadata.artist = track.artist.clone();
+		}
+		if metadata.duration.is_none() {
+			metadata.duration = track.duration;
+		}
+		metadata.source_url = Some(track.url.clone());
+
+		Ok(input)
+	}
+}
+
+/// Lists the audio files at a path, descending into directories. The file
+/// system is read on a blocking thread.
+pub(crate) async fn list_audio_files(
+	path: PathBuf,
+) -> io::Result<Vec<PathBuf>> {
+	task::spawn_blocking(move || {
+		if !path.is_dir() {
+			return Ok(vec![path]);
+		}
+
+		let mut files = Vec::new();
+		walk(&path, &mut files)?;
+		Ok(files)
+	})
+	.await
+	.map_err(io::Error::from)?
+}
+
+/// Adds the audio files inside a directory, in order of their paths.
+fn walk(directory: &Path, files: &mut Vec<PathBuf>) -> io::Result<()> {
+	let mut entries =
+		fs::read_dir(directory)?.collect::<io::Result<Vec<_>>>()?;
+	entries.sort_by_key(|entry| entry.path());
+
+	for entry in entries {
+		let path = entry.path();
+		// the file type doesn't follow links, so linked directories are
+		// skipped rather than possibly descending through a cycle
+		if entry.file_type()?.is_dir() {
+			walk(&path, files)?;
+		} else if !path.is_dir() && is_audio(&path) {
+			files.push(path);
+		}
+	}
+
+	Ok(())
+}
+
+fn is_audio(path: &Path) -> bool {
+	path.extension()
+		.and_then(|extension| extension.to_str())
+		.map(|extension| {
+			AUDIO_EXTENSIONS.contains(&extension.to_lowercase().as_str())
+		})
+		.unwrap_or(false)
+}
+
+fn invalid_input(message: &str) -> io::Error {
+	io::Error::new(io::ErrorKind::InvalidInput, message)
+}
+
+#[cfg(test)]
+mod tests {
+	use std::fs;
+
+	use super::list_audio_files;
+
+	#[cfg(unix)]
+	#[tokio::test]
+	async fn test_list_audio_files_skips_linked_directories() {
+		let root = std::env::temp_dir()
+			.join(format!("musicbot-local-{}", std::process::id()));
+		fs::create_dir_all(root.join("album")).unwrap();
+		fs::write(root.join("album").join("song.mp3"), b"").unwrap();
+		fs::write(root.join("cover.jpg"), b"").unwrap();
+		std::os::unix::fs::symlink(&root, root.join("album").join("loop"))
+			.unwrap();
+
+		let files = list_audio_files(root.clone()).await;
+		fs::remove_dir_all(&root).unwrap();
+
+		assert_eq!(files.unwrap(), [root.join("album").join("song.mp3")]);
+	}
+}
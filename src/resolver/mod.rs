@@ -0,0 +1,223 @@
+mod fake;
+mod local;
+mod youtube_dl;
+
+use std::{sync::Arc, time::Duration};
+
+use serde_json::Value;
+use serenity::{async_trait, client::Context, prelude::TypeMapKey};
+use songbird::input::{Input, Metadata};
+use url::Url;
+
+use crate::{
+	config::{Config, ResolverKind},
+	error::Result,
+};
+
+pub(crate) use fake::FakeResolver;
+pub(crate) use local::{list_audio_files, LocalResolver};
+pub(crate) use youtube_dl::YoutubeDlResolver;
+
+/// A track that has been found, but not yet turned into playable audio.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub(crate) struct TrackDescriptor {
+	pub url: String,
+	pub title: Option<String>,
+	pub artist: Option<String>,
+	pub duration: Option<Duration>,
+	pub thumbnail: Option<String>,
+}
+
+impl TrackDescriptor {
+	pub(crate) fn from_url(url: impl Into<String>) -> Self {
+		Self {
+			url: url.into(),
+			..Default::default()
+		}
+	}
+
+	/// Reads a single entry of youtube-dl's JSON output.
+	pub(crate) fn from_ytdl_json(value: &Value) -> Option<Self> {
+		let url = value
+			.get("webpage_url")
+			.or_else(|| value.get("url"))
+			.and_then(Value::as_str)?;
+		let get_str = |key| value.get(key).and_then(Value::as_str);
+
+		Some(Self {
+			url: url.to_string(),
+			title: get_str("title").map(str::to_string),
+			artist: get_str("artist")
+				.or_else(|| get_str("uploader"))
+				.map(str::to_string),
+			duration: value
+				.get("duration")
+				.and_then(Value::as_f64)
+				.map(Duration::from_secs_f64),
+			// entries of flat playlists only list every size of thumbnail
+			thumbnail: get_str("thumbnail")
+				.or_else(|| {
+					value
+						.get("thumbnails")
+						.and_then(Value::as_array)
+						.and_then(|thumbnails| thumbnails.last())
+						.and_then(|thumbnail| thumbnail.get("url"))
+						.and_then(Value::as_str)
+				})
+				.map(str::to_string),
+		})
+	}
+
+	/// Reads back the details of a track that has already been created.
+	pub(crate) fn from_metadata(metadata: &Metadata) -> Option<Self> {
+		Some(Self {
+			url: metadata.source_url.clone()?,
+			title: metadata.title.clone(),
+			artist: metadata.artist.clone(),
+			duration: metadata.duration,
+			thumbnail: metadata.thumbnail.clone(),
+		})
+	}
+
+	/// The metadata of the track's audio, which is always decoded to 48kHz
+	/// stereo.
+	pub(crate) fn to_metadata(&self) -> Metadata {
+		Metadata {
+			source_url: Some(self.url.clone()),
+			title: self.title.clone(),
+			artist: self.artist.clone(),
+			duration: self.duration,
+			thumbnail: self.thumbnail.clone(),
+			channels: Some(2),
+			sample_rate: Some(48_000),
+			..Default::default()
+		}
+	}
+}
+
+/// A source of tracks, responsible for finding tracks and producing their
+/// audio.
+#[async_trait]
+pub(crate) trait Resolver: Send + Sync {
+	/// Resolves a URL into its track, or every track of a playlist.
+	async fn resolve(&self, url: &Url) -> Result<Vec<TrackDescriptor>>;
+
+	/// Searches for up to `limit` tracks matching the query.
+	async fn search(
+		&self,
+		query: &str,
+		limit: usize,
+	) -> Result<Vec<TrackDescriptor>>;
+
+	/// Creates the playable audio for a track.
+	async fn input(&self, track: &TrackDescriptor) -> Result<Input>;
+
+	/// Finds a different track similar to the given one, for autoplay.
+	async fn related(
+		&self,
+		_track: &TrackDescriptor,
+	) -> Result<Option<TrackDescriptor>> {
+		Ok(None)
+	}
+}
+
+pub(crate) struct ResolverKey;
+
+impl TypeMapKey for ResolverKey {
+	type Value = Arc<dyn Resolver>;
+}
+
+/// Retrieves the resolver placed into the client data at startup.
+pub(crate) async fn get(ctx: &Context) -> Arc<dyn Resolver> {
+	ctx.data
+		.read()
+		.await
+		.get::<ResolverKey>()
+		.expect("Resolver placed in at initialisation.")
+		.clone()
+}
+
+/// Creates the resolver chosen in the configuration.
+pub(crate) fn from_config(config: &Config) -> Arc<dyn Resolver> {
+	match config.resolver {
+		ResolverKind::Local => Arc::new(LocalResolver::new(
+			config
+				.library_dir
+				.clone()
+				.expect("The local resolver is only allowed with a library"),
+		)),
+		ResolverKind::Fake => Arc::new(FakeResolver::default()),
+		ResolverKind::YoutubeDl => Arc::new(YoutubeDlResolver::new(
+			config.ytdl_path.clone(),
+			config.ytdl_args.clone(),
+		)),
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::time::Duration;
+
+	use serde_json::json;
+
+	use super::TrackDescriptor;
+
+	#[test]
+	fn test_from_ytdl_json() {
+		let track = TrackDescriptor::from_ytdl_json(&json!({
+			"webpage_url": "https://example.com/watch",
+			"url": "https://example.com/stream",
+			"title": "Title",
+			"uploader": "Uploader",
+			"duration": 90.5,
+			"thumbnail": "https://example.com/thumbnail.jpg",
+		}))
+		.unwrap();
+		assert_eq!(
+			track,
+			TrackDescriptor {
+				url: "https://example.com/watch".to_string(),
+				title: Some("Title".to_string()),
+				artist: Some("Uploader".to_string()),
+				duration: Some(Duration::from_secs_f64(90.5)),
+				thumbnail: Some(
+					"https://example.com/thumbnail.jpg".to_string()
+				),
+			}
+		);
+
+		let track = TrackDescriptor::from_ytdl_json(&json!({
+			"url": "https://example.com/entry",
+			"thumbnails": [
+				{ "url": "https://example.com/small.jpg" },
+				{ "url": "https://example.com/large.jpg" },
+			],
+		}))
+		.unwrap();
+		assert_eq!(track.url, "https://example.com/entry");
+		assert_eq!(
+			track.thumbnail.as_deref(),
+			Some("https://example.com/large.jpg")
+		);
+
+		assert_eq!(TrackDescriptor::from_ytdl_json(&json!({})), None);
+	}
+
+	#[test]
+	fn test_metadata_round_trip() {
+		let track = TrackDescriptor {
+			url: "https://example.com/1".to_string(),
+			title: Some("Title".to_string()),
+			artist: None,
+			duration: Some(Duration::from_secs(60)),
+			thumbnail: Some("https://example.com/1.jpg".to_string()),
+		};
+		let metadata = track.to_metadata();
+		assert_eq!(metadata.sample_rate, Some(48_000));
+		assert_eq!(TrackDescriptor::from_metadata(&metadata), Some(track));
+
+		let mut metadata = metadata;
+		metadata.source_url = None;
+		assert_eq!(TrackDescriptor::from_metadata(&metadata), None);
+	}
+}
@@ -0,0 +1,285 @@
+use std::{io, process::Stdio, time::Duration};
+
+use serde_json::Deserializer;
+use serenity::async_trait;
+use songbird::input::{
+	children_to_reader,
+	error::{Error as InputError, Result as InputResult},
+	restartable::Restart,
+	Codec, Container, Input, Metadata, Restartable,
+};
+use tokio::process::Command;
+use tracing::warn;
+use url::Url;
+
+use super::{Resolver, TrackDescriptor};
+use crate::error::{Error, Result};
+
+/// The same format selection songbird uses for its own youtube-dl inputs.
+const FORMAT: &str = "webm[abr>0]/bestaudio/best";
+
+/// Resolves tracks by shelling out to youtube-dl, or a compatible program such
+/// as yt-dlp.
+#[derive(Clone, Debug)]
+pub(crate) struct YoutubeDlResolver {
+	executable: String,
+	extra_args: Vec<String>,
+}
+
+impl YoutubeDlResolver {
+	/// Creates a resolver running `executable`, passing `extra_args` to every
+	/// invocation.
+	pub(crate) fn new(executable: String, extra_args: Vec<String>) -> Self {
+		Self {
+			executable,
+			extra_args,
+		}
+	}
+
+	/// A command for looking up tracks, which keeps the default number of
+	/// retries so that a failing site doesn't hold up the lookup forever.
+	fn command(&self) -> Command {
+		let mut command = Command::new(&self.executable);
+		command
+			.args(["--ignore-config"])
+			.args(&self.extra_args)
+			.stdin(Stdio::null());
+		command
+	}
+
+	/// Maps a failure to start youtube-dl to a more helpful error.
+	fn spawn_error(&self, e: io::Error) -> Error {
+		if e.kind() == io::ErrorKind::NotFound {
+			Error::ExtractorMissing(self.executable.clone())
+		} else {
+			Error::Io(e)
+		}
+	}
+
+	async fn run(&self, args: &[&str]) -> Result<Vec<TrackDescriptor>> {
+		let output = self
+			.command()
+			.args(args)
+			.output()
+			.await
+			.map_err(|e| self.spawn_error(e))?;
+
+		// playlists with some unavailable entries still produce output
+		if !output.status.success() && output.stdout.is_empty() {
+			return Err(Error::from_extractor_output(&output.stderr));
+		}
+
+		// youtube-dl outputs each video as an object on one line, so the
+		// into_iter method is used to process each one
+		Ok(Deserializer::from_slice(&output.stdout)
+			.into_iter::<serde_json::Value>()
+			.filter_map(|video| match video {
+				Ok(video) => Some(video),
+				Err(e) => {
+					warn!("Could not parse youtube-dl output: {}", e);
+					None
+				}
+			})
+			.filter_map(|video| {
+				let track = TrackDescriptor::from_ytdl_json(&video);
+				if track.is_none() {
+					warn!("youtube-dl entry has no URL: {}", video);
+				}
+				track
+			})
+			.collect())
+	}
+}
+
+#[async_trait]
+impl Resolver for YoutubeDlResolver {
+	async fn resolve(&self, url: &Url) -> Result<Vec<TrackDescriptor>> {
+		// the extractor knows which URLs are playlists, so any URL that
+		// produces more than one entry is treated as one
+		let mut tracks = self
+			.run(&["-j", "--flat-playlist", "--yes-playlist", url.as_str()])
+			.await?;
+
+		if tracks.is_empty() {
+			tracks.push(TrackDescriptor::from_url(url.as_str()));
+		}
+
+		Ok(tracks)
+	}
+
+	async fn search(
+		&self,
+		query: &str,
+		limit: usize,
+	) -> Result<Vec<TrackDescriptor>> {
+		self.run(&["--dump-json", &format!("ytsearch{}:{}", limit, query)])
+			.await
+	}
+
+	async fn related(
+		&self,
+		track: &TrackDescriptor,
+	) -> Result<Option<TrackDescriptor>> {
+		let id = match youtube_video_id(&track.url) {
+			Some(id) => id,
+			None => return Ok(None),
+		};
+
+		// YouTube's mixes are made of videos related to the one they start
+		// with, which is always the first entry
+		let mix = format!("https://www.youtube.com/watch?v={0}&list=RD{0}", id);
+		let tracks = self
+			.run(&["-j", "--flat-playlist", "--playlist-end", "10", &mix])
+			.await?;
+
+		Ok(tracks
+			.into_iter()
+			.find(|related| !related.url.contains(&id)))
+	}
+
+	/// Creates a lazy input, so that youtube-dl only downloads the track once
+	/// it is about to play. Tracks from playlists and searches already have
+	/// their details, so youtube-dl isn't run at all until then.
+	async fn input(&self, track: &TrackDescriptor) -> Result<Input> {
+		let restarter = YoutubeDlRestarter {
+			resolver: self.clone(),
+			track: track.clone(),
+		};
+
+		match Restartable::new(restarter, true).await {
+			Ok(restartable) => Ok(restartable.into()),
+			Err(InputError::Io(e)) => Err(self.spawn_error(e)),
+			Err(e) => Err(e.into()),
+		}
+	}
+}
+
+/// Finds the ID of a YouTube video from its URL.
+fn youtube_video_id(url: &str) -> Option<String> {
+	let url = Url::parse(url).ok()?;
+	match url.host_str()? {
+		"youtu.be" => url.path_segments()?.next().map(str::to_string),
+		host if host.ends_with("youtube.com") => url
+			.query_pairs()
+			.find(|(key, _)| key == "v")
+			.map(|(_, id)| id.into_owned()),
+		_ => None,
+	}
+}
+
+/// Recreates a youtube-dl stream, used instead of [`Restartable::ytdl`] so that
+/// the configured executable and arguments are used when seeking.
+struct YoutubeDlRestarter {
+	resolver: YoutubeDlResolver,
+	track: TrackDescriptor,
+}
+
+#[async_trait]
+impl Restart for YoutubeDlRestarter {
+	async fn call_restart(
+		&mut self,
+		time: Option<Duration>,
+	) -> InputResult<Input> {
+		// the download keeps retrying, as giving up would end the song early
+		let mut youtube_dl =
+			std::process::Command::new(&self.resolver.executable)
+				.args(["--ignore-config", "-R", "infinite", "--no-warnings"])
+				.args(&self.resolver.extra_args)
+				.args([
+					"-f",
+					FORMAT,
+					"--no-playlist",
+					&self.track.url,
+					"-o",
+					"-",
+				])
+				.stdin(Stdio::null())
+				.stderr(Stdio::null())
+				.stdout(Stdio::piped())
+				.spawn()?;
+
+		let mut ffmpeg = std::process::Command::new("ffmpeg");
+		if let Some(time) = time {
+			ffmpeg.args(["-ss", &format!("{:.3}", time.as_secs_f64())]);
+		}
+		let ffmpeg = ffmpeg
+			.args(["-i", "-"])
+			.args(["-f", "s16le", "-ac", "2", "-ar", "48000"])
+			.args(["-acodec", "pcm_f32le", "-"])
+			.stdin(youtube_dl.stdout.take().ok_or(InputError::Stdout)?)
+			.stderr(Stdio::null())
+			.stdout(Stdio::piped())
+			.spawn()?;
+
+		Ok(Input::new(
+			true,
+			children_to_reader::<f32>(vec![youtube_dl, ffmpeg]),
+			Codec::FloatPcm,
+			Container::Raw,
+			None,
+		))
+	}
+
+	async fn lazy_init(
+		&mut self,
+	) -> InputResult<(Option<Metadata>, Codec, Container)> {
+		// a bare URL needs looking up to have something to show in the queue
+		if self.track.title.is_some() {
+			return Ok((
+				Some(self.track.to_metadata()),
+				Codec::FloatPcm,
+				Container::Raw,
+			));
+		}
+
+		let output = self
+			.resolver
+			.command()
+			.args(["-j", "--no-warnings", "-f", FORMAT, "--no-playlist"])
+			.arg(&self.track.url)
+			.output()
+			.await?;
+
+		if !output.status.success() {
+			return Err(InputError::YouTubeDlRun(output));
+		}
+
+		let value =
+			serde_json::from_slice(&output.stdout).map_err(|error| {
+				InputError::Json {
+					error,
+					parsed_text: String::from_utf8_lossy(&output.stdout)
+						.into_owned(),
+				}
+			})?;
+
+		Ok((
+			Some(Metadata::from_ytdl_output(value)),
+			Codec::FloatPcm,
+			Container::Raw,
+		))
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::youtube_video_id;
+
+	#[test]
+	fn test_youtube_video_id() {
+		let id = youtube_video_id;
+
+		assert_eq!(
+			id("https://www.youtube.com/watch?v=dQw4w9WgXcQ&t=42").as_deref(),
+			Some("dQw4w9WgXcQ")
+		);
+		assert_eq!(
+			id("https://music.youtube.com/watch?v=abc").as_deref(),
+			Some("abc")
+		);
+		assert_eq!(id("https://youtu.be/xyz?t=1").as_deref(), Some("xyz"));
+		assert_eq!(id("https://www.youtube.com/playlist?list=PL1"), None);
+		assert_eq!(id("https://example.com/watch?v=abc"), None);
+		assert_eq!(id("not a url"), None);
+	}
+}
@@ -0,0 +1,522 @@
+use std::{
+	collections::HashMap, convert::Infallible, fmt, io, str::FromStr, sync::Arc,
+};
+
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use serenity::{
+	model::{
+		id::{ChannelId, GuildId, RoleId},
+		misc::Mentionable,
+	},
+	prelude::{Mutex, RwLock, TypeMap, TypeMapKey},
+	utils::{parse_channel, parse_role},
+};
+use tracing::error;
+
+/// Keeps changes to the settings from overwriting each other.
+static UPDATE_LOCK: Lazy<Mutex<()>> = Lazy::new(Default::default);
+
+/// Settings kept for each guild, which are saved to the data directory.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub(crate) struct GuildSettings {
+	/// Volume of every track in percent, from 0 to 200.
+	pub volume: u16,
+	pub mode: PlaybackMode,
+	/// Whether the bot stays in the voice channel when it is idle, known as
+	/// 24/7 mode.
+	pub always_on: bool,
+	/// Members with this role have full control over playback. Without one,
+	/// everybody does.
+	pub dj_role: Option<RoleId>,
+	/// The fraction of listeners that must vote to skip a song, for those
+	/// without full control.
+	pub skip_ratio: f64,
+	/// The most tracks the queue can hold, if limited.
+	pub max_queue: Option<usize>,
+	/// Replaces the default command prefix in the guild.
+	pub prefix: Option<String>,
+	/// The text channels that music commands can be used in. When empty, they
+	/// can be used anywhere.
+	pub text_channels: Vec<ChannelId>,
+	/// The voice channels that the bot can join. When empty, it joins any.
+	pub voice_channels: Vec<ChannelId>,
+}
+
+impl Default for GuildSettings {
+	fn default() -> Self {
+		Self {
+			volume: 100,
+			mode: PlaybackMode::default(),
+			always_on: false,
+			dj_role: None,
+			skip_ratio: 0.5,
+			max_queue: None,
+			prefix: None,
+			text_channels: Vec::new(),
+			voice_channels: Vec::new(),
+		}
+	}
+}
+
+/// What happens as tracks end.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub(crate) enum PlaybackMode {
+	/// Tracks are played once, leaving when the queue is empty.
+	Normal,
+	/// Tracks are added back to the end of the queue when they end.
+	LoopQueue,
+	/// The current track is repeated until it is skipped.
+	LoopTrack,
+	/// A related track is added when the queue runs out.
+	Autoplay,
+}
+
+impl Default for PlaybackMode {
+	fn default() -> Self {
+		Self::Normal
+	}
+}
+
+impl fmt::Display for PlaybackMode {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.write_str(match self {
+			Self::Normal => "Normal",
+			Self::LoopQueue => "Loop queue",
+			Self::LoopTrack => "Loop track",
+			Self::Autoplay => "Autoplay",
+		})
+	}
+}
+
+impl FromStr for PlaybackMode {
+	type Err = ();
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		match s.to_lowercase().as_str() {
+			"normal" | "off" => Ok(Self::Normal),
+			"queue" => Ok(Self::LoopQueue),
+			"track" => Ok(Self::LoopTrack),
+			"autoplay" => Ok(Self::Autoplay),
+			_ => Err(()),
+		}
+	}
+}
+
+impl GuildSettings {
+	/// The volume in the form used by songbird, where 1.0 is unchanged.
+	pub(crate) fn volume_multiplier(&self) -> f32 {
+		f32::from(self.volume) / 100.0
+	}
+
+	/// Shows a setting as it would be typed to change it.
+	pub(crate) fn get_value(&self, key: &str) -> Result<String, ConfigError> {
+		Ok(match key {
+			"prefix" => self.prefix.clone().unwrap_or_else(|| "default".into()),
+			"dj-role" => match self.dj_role {
+				Some(role) => role.mention().to_string(),
+				None => "none".into(),
+			},
+			"volume" => self.volume.to_string(),
+			"max-queue" => match self.max_queue {
+				Some(max) => max.to_string(),
+				None => "none".into(),
+			},
+			"skip-ratio" => self.skip_ratio.to_string(),
+			"mode" => self.mode.to_string(),
+			"24-7" => if self.always_on { "on" } else { "off" }.into(),
+			"text-channels" => show_channels(&self.text_channels),
+			"voice-channels" => show_channels(&self.voice_channels),
+			_ => return Err(ConfigError::UnknownKey(key.to_string())),
+		})
+	}
+
+	/// Changes a setting from text, as typed into the `config` command.
+	pub(crate) fn set_value(
+		&mut self,
+		key: &str,
+		value: &str,
+	) -> Result<(), ConfigError> {
+		let invalid = || ConfigError::InvalidValue(key.to_string());
+
+		match key {
+			"prefix" => {
+				if value.is_empty()
+					|| value.chars().count() > MAX_PREFIX_LENGTH
+					|| value.contains(char::is_whitespace)
+				{
+					return Err(invalid());
+				}
+				self.prefix = Some(value.to_string());
+			}
+			"dj-role" => {
+				self.dj_role = if value.eq_ignore_ascii_case("none") {
+					None
+				} else {
+					let role = parse_role(value)
+						.or_else(|| value.parse().ok())
+						.ok_or_else(invalid)?;
+					Some(RoleId(role))
+				};
+			}
+			"volume" => {
+				self.volume = value
+					.parse()
+					.ok()
+					.filter(|volume| *volume <= MAX_VOLUME)
+					.ok_or_else(invalid)?;
+			}
+			"max-queue" => {
+				self.max_queue = match value {
+					"none" | "0" => None,
+					_ => Some(value.parse().map_err(|_| invalid())?),
+				};
+			}
+			"skip-ratio" => {
+				self.skip_ratio = value
+					.parse()
+					.ok()
+					.filter(|ratio| *ratio > 0.0 && *ratio <= 1.0)
+					.ok_or_else(invalid)?;
+			}
+			"mode" => self.mode = value.parse().map_err(|_| invalid())?,
+			"24-7" => {
+				self.always_on = match value.to_lowercase().as_str() {
+					"on" | "true" => true,
+					"off" | "false" => false,
+					_ => return Err(invalid()),
+				};
+			}
+			"text-channels" => {
+				self.text_channels =
+					parse_channels(value).ok_or_else(invalid)?
+			}
+			"voice-channels" => {
+				self.voice_channels =
+					parse_channels(value).ok_or_else(invalid)?
+			}
+			_ => return Err(ConfigError::UnknownKey(key.to_string())),
+		}
+
+		Ok(())
+	}
+
+	/// Puts a setting back to its default value.
+	pub(crate) fn reset_value(&mut self, key: &str) -> Result<(), ConfigError> {
+		let default = Self::default();
+		match key {
+			"prefix" => self.prefix = default.prefix,
+			"dj-role" => self.dj_role = default.dj_role,
+			"volume" => self.volume = default.volume,
+			"max-queue" => self.max_queue = default.max_queue,
+			"skip-ratio" => self.skip_ratio = default.skip_ratio,
+			"mode" => self.mode = default.mode,
+			"24-7" => self.always_on = default.always_on,
+			"text-channels" => self.text_channels = default.text_channels,
+			"voice-channels" => self.voice_channels = default.voice_channels,
+			_ => return Err(ConfigError::UnknownKey(key.to_string())),
+		}
+
+		Ok(())
+	}
+}
+
+/// The settings that can be read and changed with the `config` command.
+pub(crate) const KEYS: [&str; 9] = [
+	"prefix",
+	"dj-role",
+	"volume",
+	"max-queue",
+	"skip-ratio",
+	"mode",
+	"24-7",
+	"text-channels",
+	"voice-channels",
+];
+
+/// The loudest the volume can be set to, in percent.
+pub(crate) const MAX_VOLUME: u16 = 200;
+
+const MAX_PREFIX_LENGTH: usize = 10;
+
+/// A setting that could not be read or changed.
+#[derive(Debug)]
+pub(crate) enum ConfigError {
+	UnknownKey(String),
+	InvalidValue(String),
+}
+
+impl fmt::Display for ConfigError {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			Self::UnknownKey(key) => write!(
+				f,
+				"There is no setting called `{}`, the settings are {}.",
+				key,
+				KEYS.map(|key| format!("`{}`", key)).join(", ")
+			),
+			Self::InvalidValue(key) => write!(
+				f,
+				"That isn't a valid value for `{}`, it must be {}.",
+				key,
+				expected_value(key)
+			),
+		}
+	}
+}
+
+/// Describes the values a setting accepts.
+fn expected_value(key: &str) -> String {
+	match key {
+		"prefix" => {
+			format!("up to {} characters without spaces", MAX_PREFIX_LENGTH)
+		}
+		"dj-role" => "a role or `none`".into(),
+		"volume" => format!("between 0 and {}", MAX_VOLUME),
+		"max-queue" => "a number of songs or `none`".into(),
+		"skip-ratio" => "between 0 and 1, such as 0.5".into(),
+		"mode" => "`normal`, `queue`, `track` or `autoplay`".into(),
+		"text-channels" | "voice-channels" => "channels or `any`".into(),
+		_ => "`on` or `off`".into(),
+	}
+}
+
+/// Lists channels as mentions, or `any` if there are none.
+fn show_channels(channels: &[ChannelId]) -> String {
+	if channels.is_empty() {
+		return "any".into();
+	}
+
+	channels
+		.iter()
+		.map(|channel| channel.mention().to_string())
+		.collect::<Vec<_>>()
+		.join(" ")
+}
+
+/// Reads channel mentions or IDs separated by spaces or commas, where `any`
+/// removes the restriction.
+fn parse_channels(value: &str) -> Option<Vec<ChannelId>> {
+	if value.eq_ignore_ascii_case("any") || value.eq_ignore_ascii_case("none") {
+		return Some(Vec::new());
+	}
+
+	let mut channels = Vec::new();
+	for channel in value.split(|c: char| c == ',' || c.is_whitespace()) {
+		if channel.is_empty() {
+			continue;
+		}
+		let channel =
+			parse_channel(channel).or_else(|| channel.parse().ok())?;
+		if !channels.contains(&ChannelId(channel)) {
+			channels.push(ChannelId(channel));
+		}
+	}
+
+	(!channels.is_empty()).then(|| channels)
+}
+
+/// The settings of each guild that have been loaded so far.
+pub(crate) struct SettingsKey;
+
+impl TypeMapKey for SettingsKey {
+	type Value = Arc<RwLock<HashMap<GuildId, GuildSettings>>>;
+}
+
+async fn loaded(
+	data: &RwLock<TypeMap>,
+) -> Arc<RwLock<HashMap<GuildId, GuildSettings>>> {
+	data.read()
+		.await
+		.get::<SettingsKey>()
+		.expect("Settings placed in at initialisation.")
+		.clone()
+}
+
+/// Retrieves the settings of a guild, loading them from disk the first time.
+///
+/// This takes the client data rather than a context so that it can also be
+/// used from voice events. The defaults are used if the settings can't be
+/// read, but aren't kept, so that a later change can't save them over the
+/// real settings.
+pub(crate) async fn get(
+	data: &RwLock<TypeMap>,
+	guild: GuildId,
+) -> GuildSettings {
+	match load(data, guild).await {
+		Ok(settings) => settings,
+		Err(e) => {
+			error!("Could not load settings of {}: {}", guild, e);
+			GuildSettings::default()
+		}
+	}
+}
+
+/// Retrieves the settings of a guild, reading them from disk if they haven't
+/// been loaded yet.
+async fn load(
+	data: &RwLock<TypeMap>,
+	guild: GuildId,
+) -> io::Result<GuildSettings> {
+	let loaded = loaded(data).await;
+	if let Some(settings) = loaded.read().await.get(&guild) {
+		return Ok(settings.clone());
+	}
+
+	let settings = crate::store::load(path(guild)).await?.unwrap_or_default();
+
+	// a change saved while reading is newer than what was read, so it is kept
+	let settings = loaded
+		.write()
+		.await
+		.entry(guild)
+		.or_insert(settings)
+		.clone();
+	Ok(settings)
+}
+
+/// Changes the settings of a guild and saves them.
+pub(crate) async fn update(
+	data: &RwLock<TypeMap>,
+	guild: GuildId,
+	change: impl FnOnce(&mut GuildSettings),
+) -> io::Result<GuildSettings> {
+	let result = try_update(data, guild, |settings| {
+		change(settings);
+		Ok::<_, Infallible>(())
+	})
+	.await?;
+
+	match result {
+		Ok((_, changed)) => Ok(changed),
+		Err(never) => match never {},
+	}
+}
+
+/// Changes the settings of a guild and saves them, unless the change fails.
+/// Gives the settings from before and after the change.
+///
+/// The change is made to the latest settings while holding a lock, so that
+/// changes made at the same time all apply.
+pub(crate) async fn try_update<E>(
+	data: &RwLock<TypeMap>,
+	guild: GuildId,
+	change: impl FnOnce(&mut GuildSettings) -> Result<(), E>,
+) -> io::Result<Result<(GuildSettings, GuildSettings), E>> {
+	let _lock = UPDATE_LOCK.lock().await;
+
+	let previous = load(data, guild).await?;
+	let mut settings = previous.clone();
+	if let Err(e) = change(&mut settings) {
+		return Ok(Err(e));
+	}
+
+	crate::store::save(path(guild), &settings).await?;
+	loaded(data)
+		.await
+		.write()
+		.await
+		.insert(guild, settings.clone());
+
+	Ok(Ok((previous, settings)))
+}
+
+fn path(guild: GuildId) -> String {
+	format!("guilds/{}.json", guild)
+}
+
+#[cfg(test)]
+mod tests {
+	use serenity::model::id::{ChannelId, RoleId};
+
+	use super::{ConfigError, GuildSettings, PlaybackMode, KEYS};
+
+	#[test]
+	fn test_set_value() {
+		let mut settings = GuildSettings::default();
+		settings.set_value("prefix", "!").unwrap();
+		settings.set_value("dj-role", "<@&123>").unwrap();
+		settings.set_value("max-queue", "50").unwrap();
+		settings
+			.set_value("text-channels", "<#10>, 20 <#10>")
+			.unwrap();
+
+		assert_eq!(settings.prefix.as_deref(), Some("!"));
+		assert_eq!(settings.dj_role, Some(RoleId(123)));
+		assert_eq!(settings.max_queue, Some(50));
+		assert_eq!(settings.text_channels, [ChannelId(10), ChannelId(20)]);
+		assert_eq!(settings.get_value("text-channels").unwrap(), "<#10> <#20>");
+
+		assert!(matches!(
+			settings.set_value("volume", "300"),
+			Err(ConfigError::InvalidValue(_))
+		));
+		assert!(matches!(
+			settings.set_value("prefix", "two words"),
+			Err(ConfigError::InvalidValue(_))
+		));
+		assert!(matches!(
+			settings.set_value("voice-channels", "general"),
+			Err(ConfigError::InvalidValue(_))
+		));
+		assert!(matches!(
+			settings.set_value("colour", "red"),
+			Err(ConfigError::UnknownKey(_))
+		));
+		assert_eq!(settings.volume, 100);
+	}
+
+	#[test]
+	fn test_volume() {
+		let mut settings = GuildSettings::default();
+		assert_eq!(settings.volume_multiplier(), 1.0);
+
+		settings.set_value("volume", "50").unwrap();
+		assert_eq!(settings.volume_multiplier(), 0.5);
+		settings.set_value("volume", "200").unwrap();
+		assert_eq!(settings.volume_multiplier(), 2.0);
+
+		// settings saved before the volume existed still play at full volume
+		let saved = serde_json::from_str::<GuildSettings>("{}").unwrap();
+		assert_eq!(saved.volume, 100);
+		let saved =
+			serde_json::from_str::<GuildSettings>(r#"{"volume": 30}"#).unwrap();
+		assert_eq!(saved.volume, 30);
+	}
+
+	#[test]
+	fn test_playback_mode() {
+		for (input, mode) in [
+			("normal", PlaybackMode::Normal),
+			("off", PlaybackMode::Normal),
+			("Queue", PlaybackMode::LoopQueue),
+			("track", PlaybackMode::LoopTrack),
+			("AUTOPLAY", PlaybackMode::Autoplay),
+		] {
+			assert_eq!(input.parse::<PlaybackMode>(), Ok(mode));
+		}
+		assert!("shuffle".parse::<PlaybackMode>().is_err());
+
+		// saved settings name the modes in full
+		let mut settings = GuildSettings::default();
+		settings.set_value("mode", "queue").unwrap();
+		assert_eq!(settings.mode, PlaybackMode::LoopQueue);
+		assert_eq!(serde_json::to_value(settings.mode).unwrap(), "loop-queue");
+	}
+
+	#[test]
+	fn test_reset_value() {
+		let mut settings = GuildSettings::default();
+		for key in KEYS {
+			let default = settings.get_value(key).unwrap();
+			settings.reset_value(key).unwrap();
+			assert_eq!(settings.get_value(key).unwrap(), default);
+		}
+
+		settings.set_value("24-7", "on").unwrap();
+		settings.reset_value("24-7").unwrap();
+		assert!(!settings.always_on);
+	}
+}
@@ -0,0 +1,266 @@
+use std::{
+	collections::HashSet,
+	io,
+	sync::{Arc, Mutex},
+	time::Duration,
+};
+
+use async_stream::stream;
+use futures_core::Stream;
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use serenity::{cache::Cache, model::id::GuildId};
+use songbird::{
+	tracks::{LoopState, Track, TrackHandle, TrackQueue},
+	Songbird,
+};
+use tracing::{debug, error, info, warn};
+
+use crate::{
+	error::Result,
+	library::{resolver_for, Library},
+	resolver::{Resolver, TrackDescriptor},
+	utils::{get_requester, PlayParameter, Requester},
+};
+
+/// Guilds with a queue saved by an earlier run of the bot that hasn't been
+/// restored or discarded yet, which is kept until the guild starts a new
+/// queue.
+static PENDING: Lazy<Mutex<HashSet<GuildId>>> = Lazy::new(Default::default);
+
+/// A single queue entry, with enough information to re-resolve it later.
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub(crate) struct SavedTrack {
+	pub url: String,
+	pub title: Option<String>,
+	pub duration: Option<Duration>,
+	/// Remaining loops on the track, `None` meaning it loops indefinitely.
+	pub loops: Option<usize>,
+	pub position: Duration,
+	/// Missing from queues saved by older versions.
+	#[serde(default)]
+	pub requester: Option<Requester>,
+	#[serde(default)]
+	pub thumbnail: Option<String>,
+}
+
+impl SavedTrack {
+	async fn capture(handle: &TrackHandle) -> Option<Self> {
+		let metadata = handle.metadata();
+		let url = metadata.source_url.clone()?;
+		let (loops, position) = match handle.get_info().await {
+			Ok(state) => (
+				match state.loops {
+					LoopState::Infinite => None,
+					LoopState::Finite(loops) => Some(loops),
+				},
+				state.position,
+			),
+			// the track has already finished, so there is nothing to save
+			Err(_) => return None,
+		};
+
+		Some(Self {
+			url,
+			title: metadata.title.clone(),
+			duration: metadata.duration,
+			loops,
+			position,
+			requester: get_requester(handle).await,
+			thumbnail: metadata.thumbnail.clone(),
+		})
+	}
+
+	async fn apply(&self, handle: &TrackHandle) {
+		if let Some(requester) = self.requester {
+			handle
+				.typemap()
+				.write()
+				.await
+				.insert::<Requester>(requester);
+		}
+
+		let result = match self.loops {
+			Some(0) => Ok(()),
+			Some(loops) => handle.loop_for(loops),
+			None => handle.enable_loop(),
+		}
+		.and_then(|_| {
+			if self.position.is_zero() {
+				Ok(())
+			} else {
+				handle.seek_time(self.position)
+			}
+		});
+
+		if let Err(e) = result {
+			warn!("Could not restore playback state of <{}>: {}", self.url, e);
+		}
+	}
+}
+
+/// The state of a guild's queue, as saved to the data directory.
+#[derive(Debug, Default, PartialEq, Serialize, Deserialize)]
+pub(crate) struct QueueSnapshot {
+	pub tracks: Vec<SavedTrack>,
+}
+
+impl QueueSnapshot {
+	pub(crate) async fn capture(queue: &TrackQueue) -> Self {
+		let mut tracks = Vec::new();
+		for handle in queue.current_queue() {
+			if let Some(track) = SavedTrack::capture(&handle).await {
+				tracks.push(track);
+			}
+		}
+
+		Self { tracks }
+	}
+
+	pub(crate) async fn load(guild: GuildId) -> io::Result<Option<Self>> {
+		crate::store::load(Self::path(guild)).await
+	}
+
+	pub(crate) async fn save(&self, guild: GuildId) -> io::Result<()> {
+		crate::store::save(Self::path(guild), self).await
+	}
+
+	pub(crate) async fn discard(guild: GuildId) {
+		PENDING.lock().unwrap().remove(&guild);
+		if let Err(e) = crate::store::remove(Self::path(guild)).await {
+			error!("Could not remove saved queue of {}: {}", guild, e);
+		}
+	}
+
+	/// Re-resolves every saved track, restoring their loops and position.
+	/// Tracks from the music library are played from it instead.
+	pub(crate) fn get_tracks(
+		self,
+		resolver: Arc<dyn Resolver>,
+		library: Option<Arc<Library>>,
+	) -> impl Stream<Item = Result<(Track, TrackHandle)>> {
+		stream! {
+			for saved in self.tracks {
+				let resolver =
+					resolver_for(&saved.url, &resolver, library.as_ref());
+
+				let track = TrackDescriptor {
+					title: saved.title.clone(),
+					duration: saved.duration,
+					thumbnail: saved.thumbnail.clone(),
+					..TrackDescriptor::from_url(saved.url.clone())
+				};
+				for await result in
+					PlayParameter::Tracks(vec![track]).get_tracks(resolver)
+				{
+					if let Ok((_, ref handle)) = result {
+						saved.apply(handle).await;
+					}
+					yield result;
+				}
+			}
+		}
+	}
+
+	fn path(guild: GuildId) -> String {
+		format!("queues/{}.json", guild)
+	}
+}
+
+/// Remembers the guilds with a queue saved before the bot started, so that
+/// they are kept until restored. This should be done once at startup.
+pub(crate) async fn find_pending() -> io::Result<()> {
+	let guilds = crate::store::list("queues")
+		.await?
+		.into_iter()
+		.filter_map(|name| name.parse().ok().map(GuildId));
+
+	PENDING.lock().unwrap().extend(guilds);
+	Ok(())
+}
+
+/// Saves the queue of every guild the bot is currently playing in. A new
+/// queue replaces the one left from before the bot started, as otherwise it
+/// would never be saved.
+pub(crate) async fn save_all(cache: &Cache, manager: &Songbird) {
+	for guild in cache.guilds().await {
+		let handler_lock = match manager.get(guild) {
+			Some(handler_lock) => handler_lock,
+			None => continue,
+		};
+
+		let queue = handler_lock.lock().await.queue().clone();
+		let snapshot = QueueSnapshot::capture(&queue).await;
+		if snapshot.tracks.is_empty() {
+			continue;
+		}
+
+		if PENDING.lock().unwrap().remove(&guild) {
+			info!(
+				"Replacing the unrestored queue of {} with its new one",
+				guild
+			);
+		}
+
+		match snapshot.save(guild).await {
+			Ok(_) => debug!(
+				"Saved {} track(s) from {}'s queue",
+				snapshot.tracks.len(),
+				guild
+			),
+			Err(e) => error!("Could not save {}'s queue: {}", guild, e),
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::time::Duration;
+
+	use serenity::model::id::{ChannelId, GuildId, UserId};
+
+	use super::{find_pending, QueueSnapshot, SavedTrack, PENDING};
+	use crate::{
+		config::{self, Config},
+		utils::Requester,
+	};
+
+	#[tokio::test]
+	async fn test_save_and_load() {
+		let dir = std::env::temp_dir()
+			.join(format!("musicbot-snapshot-{}", std::process::id()));
+		config::set(Config {
+			data_dir: dir.clone(),
+			..Config::default()
+		});
+
+		let guild = GuildId(1);
+		let snapshot = QueueSnapshot {
+			tracks: vec![SavedTrack {
+				url: "https://example.com/1".to_string(),
+				title: Some("Track 1".to_string()),
+				duration: Some(Duration::from_secs(200)),
+				loops: Some(2),
+				position: Duration::from_secs(42),
+				requester: Some(Requester {
+					user: UserId(2),
+					channel: ChannelId(3),
+				}),
+				thumbnail: Some("https://example.com/1.jpg".to_string()),
+			}],
+		};
+		snapshot.save(guild).await.unwrap();
+
+		let loaded = QueueSnapshot::load(guild).await.unwrap();
+		find_pending().await.unwrap();
+		let pending = PENDING.lock().unwrap().contains(&guild);
+		QueueSnapshot::discard(guild).await;
+		let discarded = QueueSnapshot::load(guild).await.unwrap();
+		std::fs::remove_dir_all(&dir).unwrap();
+
+		assert_eq!(loaded, Some(snapshot));
+		assert!(pending);
+		assert!(!PENDING.lock().unwrap().contains(&guild));
+		assert_eq!(discarded, None);
+	}
+}
@@ -0,0 +1,92 @@
+use std::{
+	io::{self, ErrorKind},
+	path::{Path, PathBuf},
+	sync::atomic::{AtomicU64, Ordering},
+};
+
+use serde::{de::DeserializeOwned, Serialize};
+use tokio::fs;
+
+use crate::config;
+
+/// Gives each write its own temporary file, so that writes to the same
+/// document at the same time don't interfere.
+static NEXT_TEMP: AtomicU64 = AtomicU64::new(0);
+
+/// Resolves a path relative to the configured data directory.
+pub(crate) fn data_path(relative: impl AsRef<Path>) -> PathBuf {
+	config::get().data_dir.join(relative)
+}
+
+/// Reads a JSON document from the data directory, returning `None` if it does
+/// not exist.
+pub(crate) async fn load<T: DeserializeOwned>(
+	relative: impl AsRef<Path>,
+) -> io::Result<Option<T>> {
+	match fs::read(data_path(relative)).await {
+		Ok(contents) => serde_json::from_slice(&contents)
+			.map(Some)
+			.map_err(|e| io::Error::new(ErrorKind::InvalidData, e)),
+		Err(e) if e.kind() == ErrorKind::NotFound => Ok(None),
+		Err(e) => Err(e),
+	}
+}
+
+/// Writes a JSON document to the data directory.
+///
+/// The document is written to a temporary file first and then renamed into
+/// place, so a crash part way through never leaves a truncated file behind.
+pub(crate) async fn save<T: Serialize>(
+	relative: impl AsRef<Path>,
+	value: &T,
+) -> io::Result<()> {
+	let path = data_path(relative);
+	if let Some(parent) = path.parent() {
+		fs::create_dir_all(parent).await?;
+	}
+
+	let contents = serde_json::to_vec_pretty(value)
+		.map_err(|e| io::Error::new(ErrorKind::InvalidData, e))?;
+	let temp_path = path.with_extension(format!(
+		"{}.tmp",
+		NEXT_TEMP.fetch_add(1, Ordering::Relaxed)
+	));
+	fs::write(&temp_path, contents).await?;
+	fs::rename(temp_path, path).await
+}
+
+/// The names of the documents in a directory of the data directory, without
+/// their extension. A missing directory has no documents.
+pub(crate) async fn list(
+	relative: impl AsRef<Path>,
+) -> io::Result<Vec<String>> {
+	let mut entries = match fs::read_dir(data_path(relative)).await {
+		Ok(entries) => entries,
+		Err(e) if e.kind() == ErrorKind::NotFound => return Ok(Vec::new()),
+		Err(e) => return Err(e),
+	};
+
+	let mut names = Vec::new();
+	while let Some(entry) = entries.next_entry().await? {
+		let path = entry.path();
+		if path
+			.extension()
+			.map_or(false, |extension| extension == "json")
+		{
+			if let Some(name) = path.file_stem().and_then(|name| name.to_str())
+			{
+				names.push(name.to_string());
+			}
+		}
+	}
+
+	Ok(names)
+}
+
+/// Removes a document from the data directory, ignoring missing files.
+pub(crate) async fn remove(relative: impl AsRef<Path>) -> io::Result<()> {
+	match fs::remove_file(data_path(relative)).await {
+		Err(e) if e.kind() != ErrorKind::NotFound => Err(e),
+		_ => Ok(()),
+	}
+}
@@ -1,30 +1,41 @@
-use std::{borrow::Cow, fmt::Display, future::Future, time::Duration};
+use std::{
+	borrow::Cow,
+	fmt::{self, Display},
+	future::Future,
+	ops::RangeInclusive,
+	sync::Arc,
+	time::{Duration, Instant},
+};
 
-use async_stream::{stream, try_stream};
+use async_stream::stream;
 use futures_core::Stream;
 use futures_util::stream::{FuturesOrdered, StreamExt};
-use itertools::Itertools;
 use once_cell::sync::Lazy;
 use regex::Regex;
-use serde_json::Deserializer;
+use serde::{Deserialize, Serialize};
 use serenity::{
-	model::{
-		channel::Message,
-		id::{ChannelId, GuildId},
-	},
+	async_trait,
+	cache::Cache,
+	model::id::{ChannelId, GuildId, UserId},
 	prelude::*,
 	utils::{EmbedMessageBuilding, MessageBuilder},
 };
 use songbird::{
-	input::{error::Result as SongbirdResult, Metadata, Restartable},
-	tracks::{create_player, Track, TrackHandle},
+	input::Metadata,
+	tracks::{create_player, Track, TrackHandle, TrackQueue},
 	Call,
 };
-use tokio::{process::Command, sync::MutexGuard};
-use tracing::{error, info, warn};
+use tokio::sync::{watch, MutexGuard};
+use tracing::{error, info};
 use url::Url;
 
-use crate::QUEUE_CHUNK_SIZE;
+use crate::{
+	config,
+	error::{Error, Result},
+	idle::{Idle, IdleReason},
+	resolver::{Resolver, TrackDescriptor},
+	settings::GuildSettings,
+};
 
 pub(crate) trait ObtainTitle {
 	fn get_title(&self) -> &str;
@@ -39,175 +50,416 @@ impl ObtainTitle for TrackHandle {
 	}
 }
 
+/// Who queued a track and where from, kept in the typemap of its
+/// [`TrackHandle`].
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub(crate) struct Requester {
+	pub user: UserId,
+	pub channel: ChannelId,
+}
+
+impl TypeMapKey for Requester {
+	type Value = Requester;
+}
+
+pub(crate) async fn get_requester(track: &TrackHandle) -> Option<Requester> {
+	track.typemap().read().await.get::<Requester>().copied()
+}
+
+/// Marks a track that was taken out of the queue on purpose, so that looping
+/// the queue doesn't add it back.
+pub(crate) struct Dequeued;
+
+impl TypeMapKey for Dequeued {
+	type Value = ();
+}
+
+pub(crate) async fn mark_dequeued(track: &TrackHandle) {
+	track.typemap().write().await.insert::<Dequeued>(());
+}
+
+pub(crate) async fn is_dequeued(track: &TrackHandle) -> bool {
+	track.typemap().read().await.contains_key::<Dequeued>()
+}
+
+/// The queue of the guild, which is empty if the bot isn't in a call there.
+pub(crate) async fn get_current_queue(
+	ctx: &Context,
+	guild_id: GuildId,
+) -> Vec<TrackHandle> {
+	let manager = songbird::get(ctx)
+		.await
+		.expect("Songbird Voice Client placed in at initialisation.")
+		.clone();
+
+	match manager.get(guild_id) {
+		Some(handler_lock) => handler_lock.lock().await.queue().current_queue(),
+		None => Vec::new(),
+	}
+}
+
 pub(crate) async fn get_user_server_channel(
 	ctx: &Context,
-	msg: &Message,
+	guild: Option<GuildId>,
+	user: UserId,
 ) -> Option<(GuildId, ChannelId)> {
-	let guild = msg.guild(&ctx.cache).await?;
+	let guild = guild?.to_guild_cached(&ctx.cache).await?;
 
 	Some((
 		guild.id,
 		guild
 			.voice_states
-			.get(&msg.author.id)
+			.get(&user)
 			.and_then(|voice_state| voice_state.channel_id)?,
 	))
 }
 
+/// The users other than bots in a voice channel.
+pub(crate) async fn get_listeners(
+	cache: &Cache,
+	guild: GuildId,
+	channel: ChannelId,
+) -> Vec<UserId> {
+	let users = cache
+		.guild_field(guild, |guild| {
+			guild
+				.voice_states
+				.values()
+				.filter(|state| state.channel_id == Some(channel))
+				.map(|state| state.user_id)
+				.collect::<Vec<_>>()
+		})
+		.await
+		.unwrap_or_default();
+
+	let mut listeners = Vec::new();
+	for user in users {
+		// users missing from the cache are assumed to be people
+		if !cache.user(user).await.map_or(false, |user| user.bot) {
+			listeners.push(user);
+		}
+	}
+
+	listeners
+}
+
 pub(crate) enum PlayParameter {
 	MaybeUrl(String),
-	Url(String),
+	/// Tracks that have already been found.
+	Tracks(Vec<TrackDescriptor>),
 }
 
 impl PlayParameter {
+	/// Finds the tracks to play, without creating their audio yet.
+	pub(crate) async fn resolve(
+		self,
+		resolver: &dyn Resolver,
+	) -> Result<Vec<TrackDescriptor>> {
+		match self {
+			Self::Tracks(tracks) => Ok(tracks),
+			Self::MaybeUrl(potential_url) => match Url::parse(&potential_url) {
+				Ok(url) => resolver.resolve(&url).await,
+				Err(_) => match resolver.search(&potential_url, 1).await {
+					Ok(tracks) if tracks.is_empty() => Err(Error::NoResults),
+					result => result,
+				},
+			},
+		}
+	}
+
 	pub(crate) fn get_tracks(
 		self,
-	) -> impl Stream<Item = SongbirdResult<(Track, TrackHandle)>> {
+		resolver: Arc<dyn Resolver>,
+	) -> impl Stream<Item = Result<(Track, TrackHandle)>> {
 		stream! {
-			match self {
-				Self::Url(url) => {
-					yield Restartable::ytdl(url, true)
-						.await
-						.map(|song| create_player(song.into()));
-				}
-				Self::MaybeUrl(potential_url) => match Url::parse(&potential_url) {
-					Ok(url) => {
-						for await result in Self::handle_url(url) {
-							yield result;
-						}
+			match self.resolve(resolver.as_ref()).await {
+				Ok(tracks) => {
+					for await result in create_players(resolver.as_ref(), &tracks) {
+						yield result;
 					}
-					Err(_) => {
-						yield Restartable::ytdl_search(potential_url, true)
-							.await
-							.map(|song| create_player(song.into()));
-					}
-				},
+				}
+				Err(e) => yield Err(e),
 			}
 		}
 	}
+}
 
-	fn handle_url(
-		url: Url,
-	) -> impl Stream<Item = SongbirdResult<(Track, TrackHandle)>> {
-		const KNOWN_PLAYLIST_HOSTS: [&str; 3] =
-			["youtube.com", "music.youtube.com", "www.youtube.com"];
-
-		let is_playlist = url
-			.host_str()
-			.map(|host| KNOWN_PLAYLIST_HOSTS.contains(&host))
-			.unwrap_or(false)
-			&& url.path() == "/playlist"
-			&& url.query_pairs().any(|(key, _)| key == "list");
-
-		try_stream! {
-			if is_playlist {
-				let ytdl = Command::new("youtube-dl")
-					.args(&["-j", "--flat-playlist", "--ignore-config"])
-					.arg(url.as_str())
-					.output()
-					.await?;
-
-				let mut song_iter = Deserializer::from_slice(&ytdl.stdout)
-					.into_iter::<serde_json::Value>()
-					.filter_map(|video| video.ok())
-					.map(|video| {
-						let url = video
-							.get("url")
-							.expect("youtube-dl JSON has no 'url' field")
-							.as_str()
-							.expect("youtube-dl JSON has wrong 'url' field type")
-							.to_string();
-						Restartable::ytdl(url, true)
-					});
-
-				match song_iter.next() {
-					Some(song) => {
-						yield create_player(song.await?.into());
-
-						let songs = song_iter
-							.chunks(*QUEUE_CHUNK_SIZE)
-							.into_iter()
-							.map(|chunk| chunk.collect::<FuturesOrdered<_>>())
-							.collect::<Vec<_>>();
-
-						for mut song_chunk in songs {
-							while let Some(song) = song_chunk.next().await {
-								yield create_player(song?.into());
-							}
-						}
-					}
-					None => {}
+/// Removes the playlist from a URL that links to a single video inside of a
+/// playlist, such as `watch?v=...&list=...`, so that only the video is played.
+/// Anything else is returned unchanged.
+pub(crate) fn without_playlist(query: &str) -> Cow<'_, str> {
+	let mut url = match Url::parse(query) {
+		Ok(url) => url,
+		Err(_) => return Cow::Borrowed(query),
+	};
+
+	let has_key = |key| url.query_pairs().any(|(name, _)| name == key);
+	if !has_key("v") || !has_key("list") {
+		return Cow::Borrowed(query);
+	}
+
+	let pairs = url
+		.query_pairs()
+		.filter(|(key, _)| key != "list" && key != "index")
+		.map(|(key, value)| (key.into_owned(), value.into_owned()))
+		.collect::<Vec<_>>();
+	url.query_pairs_mut().clear().extend_pairs(pairs);
+
+	Cow::Owned(url.into())
+}
+
+/// Creates the audio of each track in order, working on the configured chunk
+/// size of tracks at a time after the first.
+fn create_players<'a>(
+	resolver: &'a dyn Resolver,
+	tracks: &'a [TrackDescriptor],
+) -> impl Stream<Item = Result<(Track, TrackHandle)>> + 'a {
+	stream! {
+		if let Some((first, rest)) = tracks.split_first() {
+			yield create_player_for(resolver, first).await;
+
+			let chunk_size = config::get().queue_chunk_size;
+			for chunk in rest.chunks(chunk_size) {
+				let mut inputs = chunk
+					.iter()
+					.map(|track| create_player_for(resolver, track))
+					.collect::<FuturesOrdered<_>>();
+
+				while let Some(input) = inputs.next().await {
+					yield input;
 				}
-			} else {
-				yield create_player(Restartable::ytdl(url, true).await?.into());
 			}
 		}
 	}
 }
 
+async fn create_player_for(
+	resolver: &dyn Resolver,
+	track: &TrackDescriptor,
+) -> Result<(Track, TrackHandle)> {
+	resolver
+		.input(track)
+		.await
+		.map(create_player)
+		.map_err(|e| e.for_track(track))
+}
+
+/// Something that tracks can be queued into. Each method only locks the queue
+/// for as long as it needs, so that it isn't held while tracks are resolved.
+#[async_trait]
+pub(crate) trait EnqueueTrack {
+	async fn enqueue_track(&self, track: Track);
+	async fn queue_len(&self) -> usize;
+}
+
+#[async_trait]
+impl EnqueueTrack for Mutex<Call> {
+	async fn enqueue_track(&self, track: Track) {
+		let handle = track.handle.clone();
+		let mut handler = self.lock().await;
+		handler.enqueue(track);
+
+		// tracks further back are prefetched as the queue moves along
+		if handler.queue().len() <= config::get().prefetch_tracks + 1 {
+			let _ = handle.make_playable();
+		}
+	}
+
+	async fn queue_len(&self) -> usize {
+		self.lock().await.queue().len()
+	}
+}
+
+/// Starts loading the audio of the tracks at the front of the queue, so that
+/// each can play as soon as the one before it ends.
+pub(crate) fn prefetch(queue: &TrackQueue) {
+	let count = config::get().prefetch_tracks + 1;
+	for track in queue.current_queue().iter().take(count) {
+		let _ = track.make_playable();
+	}
+}
+
+/// How far queueing a stream of tracks has got, shown while a playlist loads.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub(crate) struct Progress {
+	/// How many tracks will be loaded in total, if known beforehand.
+	pub total: Option<usize>,
+	pub loaded: usize,
+	pub failed: usize,
+	pub elapsed: Duration,
+}
+
+impl fmt::Display for Progress {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(f, "Loaded {}", self.loaded)?;
+		if let Some(total) = self.total {
+			write!(f, " of {}", total)?;
+		}
+		write!(f, " song(s) in {}", format_duration(self.elapsed))?;
+		if self.failed > 0 {
+			write!(f, ", {} failed so far", self.failed)?;
+		}
+
+		Ok(())
+	}
+}
+
+/// The most characters of failed tracks listed after queueing, so that a
+/// playlist full of deleted videos doesn't produce a message too long to send.
+const MAX_FAILURES_LENGTH: usize = 2000;
+
+/// Queues each track as soon as it has been created, so that the first can
+/// start playing while the rest of a playlist loads. The progress is sent after
+/// every track, if anything is following it.
 pub(crate) async fn queue_songs(
-	handler: &mut MutexGuard<'_, Call>,
-	song_stream: impl Stream<Item = SongbirdResult<(Track, TrackHandle)>>,
-) -> Result<String, &'static str> {
-	let ((mut message, added_songs, error), elapsed) =
+	handler: &impl EnqueueTrack,
+	song_stream: impl Stream<Item = Result<(Track, TrackHandle)>>,
+	requester: Requester,
+	settings: &GuildSettings,
+	progress: Option<&watch::Sender<Progress>>,
+) -> Result<String> {
+	let volume = settings.volume_multiplier();
+
+	let ((mut message, added_songs, mut errors, full, cancelled), elapsed) =
 		time_section(|| async move {
 			tokio::pin!(song_stream);
 
-			let mut error = false;
-			let mut song_count = 0;
+			let started = Instant::now();
+			let mut current =
+				progress.map_or_else(Progress::default, |p| p.borrow().clone());
+			// failures are kept with their position in the stream, so that
+			// they can be found in the playlist
+			let mut errors = Vec::new();
 			let mut message = MessageBuilder::new();
-
-			if let Some(song) = song_stream.next().await {
-				match song {
-					Ok((track, track_handle)) => {
-						handler.enqueue(track);
-						song_count += 1;
-						info!("Track <{}> queued", track_handle.get_title());
-						message.push(build_description(
-							track_handle.get_title(),
-							track_handle.metadata(),
-						));
-					}
-					Err(e) => {
-						error!("Error occurred during video download: {}", e);
-						error = true;
+			let mut first = true;
+			let mut full = false;
+			let mut cancelled = false;
+
+			loop {
+				// the rest of a playlist isn't resolved once the queue is full
+				if let Some(max) = settings.max_queue {
+					if handler.queue_len().await >= max {
+						full = true;
+						break;
 					}
 				}
-			}
 
-			while let Some(song) = song_stream.next().await {
+				let song = match song_stream.next().await {
+					Some(song) => song,
+					None => break,
+				};
+
 				match song {
-					Ok((track, track_handle)) => {
-						handler.enqueue(track);
-						song_count += 1;
+					Ok((mut track, track_handle)) => {
+						track.set_volume(volume);
+						track_handle
+							.typemap()
+							.write()
+							.await
+							.entry::<Requester>()
+							.or_insert(requester);
+						handler.enqueue_track(track).await;
+						current.loaded += 1;
 						info!("Track <{}> queued", track_handle.get_title());
+						if first {
+							message.push(build_description(
+								track_handle.get_title(),
+								track_handle.metadata(),
+							));
+						}
+					}
+					Err(Error::Cancelled) => {
+						cancelled = true;
+						break;
 					}
 					Err(e) => {
-						error!("Error occurred during video download: {}", e);
-						error = true;
+						error!("Error occurred during video download: {:?}", e);
+						current.failed += 1;
+						errors.push((current.loaded + current.failed, e));
 					}
 				}
+
+				first = false;
+				current.elapsed = started.elapsed();
+				if let Some(progress) = progress {
+					let _ = progress.send(current.clone());
+				}
 			}
 
-			(message, song_count, error)
+			(message, current.loaded, errors, full, cancelled)
 		})
 		.await;
 
+	if added_songs == 0 && full {
+		return Err(Error::QueueFull(settings.max_queue.unwrap_or_default()));
+	}
+
+	if added_songs == 0 && cancelled {
+		return Err(Error::Cancelled);
+	}
+
 	if added_songs == 0 {
-		Err("Error downloading songs")
-	} else {
-		message.push(format!(
-			"\n\nAdded {} song(s) in {}",
-			added_songs,
-			format_duration(elapsed)
-		));
-
-		if error {
-			message.push("\nSome songs may have been skipped due to errors");
-		}
+		return Err(if errors.is_empty() {
+			Error::NoResults
+		} else {
+			errors.swap_remove(0).1
+		});
+	}
+
+	message.push(format!(
+		"\n\nAdded {} song(s) in {}",
+		added_songs,
+		format_duration(elapsed)
+	));
+
+	if full {
+		message.push(
+			"\n\nThe queue is full, so the rest of the songs were not added.",
+		);
+	}
 
-		Ok(message.build())
+	if cancelled {
+		message.push(
+			"\n\nLoading was cancelled, so the rest of the songs were not added.",
+		);
 	}
+
+	if !errors.is_empty() {
+		message.push_line("\nSome songs were skipped due to errors:");
+		let mut length = 0;
+		for (listed, (position, error)) in errors.iter().enumerate() {
+			let line = format!("{}. {}", position, error);
+			length += line.len();
+			if length > MAX_FAILURES_LENGTH {
+				message
+					.push_line(format!("and {} more", errors.len() - listed));
+				break;
+			}
+			message.push("- ").push_line_safe(line);
+		}
+	}
+
+	Ok(message.build())
+}
+
+/// Parses queue positions given as numbers and ranges, separated by spaces or
+/// commas, such as `3 5-7,9`.
+pub(crate) fn parse_positions(
+	input: &str,
+) -> Option<Vec<RangeInclusive<usize>>> {
+	let ranges = input
+		.split(|c: char| c == ',' || c.is_whitespace())
+		.filter(|part| !part.is_empty())
+		.map(|part| match part.split_once('-') {
+			Some((start, end)) => {
+				let (start, end) = (start.parse().ok()?, end.parse().ok()?);
+				(start <= end).then(|| start..=end)
+			}
+			None => part.parse().ok().map(|position| position..=position),
+		})
+		.collect::<Option<Vec<_>>>()?;
+
+	(!ranges.is_empty()).then(|| ranges)
 }
 
 pub(crate) fn format_duration(duration: Duration) -> String {
@@ -222,12 +474,91 @@ pub(crate) fn format_duration(duration: Duration) -> String {
 	}
 }
 
+/// Draws how far through a track the position is, `width` characters wide.
+pub(crate) fn progress_bar(
+	position: Duration,
+	total: Duration,
+	width: usize,
+) -> String {
+	if width == 0 {
+		return String::new();
+	}
+
+	let filled = if total.is_zero() {
+		0
+	} else {
+		let progress = position.as_secs_f64() / total.as_secs_f64();
+		((progress.min(1.0) * width as f64) as usize).min(width - 1)
+	};
+
+	let mut bar = "▬".repeat(filled);
+	bar.push('🔘');
+	bar.push_str(&"▬".repeat(width - 1 - filled));
+	bar
+}
+
+/// Parses a timestamp written as `1:23`, `1h2m3s` or plain seconds, the
+/// reverse of [`format_duration`].
+pub(crate) fn parse_duration(input: &str) -> Option<Duration> {
+	static UNITS: Lazy<Regex> = Lazy::new(|| {
+		Regex::new(r"^(?:(\d+)h)?(?:(\d+)m)?(?:(\d+)s)?$").unwrap()
+	});
+
+	let input = input.trim();
+	let seconds = if input.contains(':') {
+		let parts = input
+			.split(':')
+			.map(|part| part.parse::<u64>().ok())
+			.collect::<Option<Vec<_>>>()?;
+
+		// only the largest unit may go past 59
+		if parts.len() > 3 || parts[1..].iter().any(|&part| part >= 60) {
+			return None;
+		}
+		parts.iter().fold(0u64, |total, part| {
+			total.saturating_mul(60).saturating_add(*part)
+		})
+	} else if let Ok(seconds) = input.parse::<u64>() {
+		seconds
+	} else {
+		let captures = UNITS.captures(input).filter(|_| !input.is_empty())?;
+		let unit = |index| {
+			captures
+				.get(index)
+				.map_or(Some(0), |unit| unit.as_str().parse::<u64>().ok())
+		};
+		unit(1)?
+			.saturating_mul(3600)
+			.saturating_add(unit(2)?.saturating_mul(60))
+			.saturating_add(unit(3)?)
+	};
+
+	Some(Duration::from_secs(seconds))
+}
+
+// kept as written before newer toolchains added this lint
+#[allow(unknown_lints, mismatched_lifetime_syntaxes)]
 pub(crate) fn escape_markdown(text: &str) -> Cow<str> {
 	static REGEX: Lazy<Regex> =
 		Lazy::new(|| Regex::new(r"([*_`~\\\[\]])").unwrap());
 	REGEX.replace_all(text, r"\$1")
 }
 
+/// Shortens text to at most `max` characters, ending it with an ellipsis if
+/// anything was cut off.
+pub(crate) fn truncate(text: &str, max: usize) -> Cow<'_, str> {
+	match text.char_indices().nth(max) {
+		None => Cow::Borrowed(text),
+		Some(_) => {
+			let end = text
+				.char_indices()
+				.nth(max.saturating_sub(1))
+				.map_or(0, |(index, _)| index);
+			Cow::Owned(format!("{}…", &text[..end]))
+		}
+	}
+}
+
 pub(crate) fn build_description<T>(
 	title: T,
 	metadata: &Metadata,
@@ -268,6 +599,7 @@ where
 	(result, timer.elapsed())
 }
 
+/// Starts the timer for leaving the voice channel if nothing was queued.
 pub(crate) async fn leave_if_empty(
 	ctx: &Context,
 	handler: MutexGuard<'_, Call>,
@@ -278,25 +610,67 @@ pub(crate) async fn leave_if_empty(
 	}
 
 	drop(handler);
-	let manager = songbird::get(ctx)
+	Idle::from_context(ctx)
 		.await
-		.expect("Cannot obtain Songbird voice client")
-		.clone();
-
-	while let Err(e) = manager.remove(guild).await {
-		warn!(
-			"Could not leave voice channel: {}, trying again in 5 seconds",
-			e
-		);
-		tokio::time::sleep(Duration::from_secs(5)).await;
-	}
+		.start(guild, IdleReason::QueueEmpty)
+		.await;
 }
 
 #[cfg(test)]
 mod tests {
-	use std::time::Duration;
+	use std::{sync::Arc, time::Duration};
+
+	use futures_util::stream::{self, StreamExt};
+	use serenity::{
+		async_trait,
+		model::id::{ChannelId, UserId},
+		prelude::Mutex,
+	};
+	use songbird::tracks::Track;
+	use tokio::sync::watch;
+
+	use super::{
+		format_duration, get_requester, parse_duration, parse_positions,
+		progress_bar, queue_songs, truncate, without_playlist, EnqueueTrack,
+		PlayParameter, Progress, Requester,
+	};
+	use crate::{
+		error::Error,
+		resolver::{FakeResolver, TrackDescriptor},
+		settings::GuildSettings,
+	};
+
+	const PLAYLIST: &str = "https://example.com/playlist";
+	const REQUESTER: Requester = Requester {
+		user: UserId(1),
+		channel: ChannelId(2),
+	};
+
+	#[async_trait]
+	impl EnqueueTrack for Mutex<Vec<Track>> {
+		async fn enqueue_track(&self, track: Track) {
+			self.lock().await.push(track);
+		}
 
-	use super::format_duration;
+		async fn queue_len(&self) -> usize {
+			self.lock().await.len()
+		}
+	}
+
+	fn playlist_resolver() -> FakeResolver {
+		FakeResolver::default().with_playlist(
+			PLAYLIST,
+			(1..=3)
+				.map(|index| TrackDescriptor {
+					title: Some(format!("Track {}", index)),
+					..TrackDescriptor::from_url(format!(
+						"https://example.com/{}",
+						index
+					))
+				})
+				.collect(),
+		)
+	}
 
 	#[test]
 	fn test_format_duration() {
@@ -304,4 +678,234 @@ mod tests {
 		assert_eq!(format_duration(Duration::from_secs(60)), "1:00");
 		assert_eq!(format_duration(Duration::from_secs(3600)), "1:00:00");
 	}
+
+	#[test]
+	fn test_parse_duration() {
+		let secs = Duration::from_secs;
+		assert_eq!(parse_duration("83"), Some(secs(83)));
+		assert_eq!(parse_duration("1:23"), Some(secs(83)));
+		assert_eq!(parse_duration("1:02:03"), Some(secs(3723)));
+		assert_eq!(parse_duration("1h2m3s"), Some(secs(3723)));
+		assert_eq!(parse_duration("2m"), Some(secs(120)));
+		assert_eq!(parse_duration("1:60"), None);
+		assert_eq!(parse_duration("1m2h"), None);
+		assert_eq!(parse_duration(""), None);
+
+		for duration in [secs(8), secs(60), secs(3723)] {
+			assert_eq!(
+				parse_duration(&format_duration(duration)),
+				Some(duration)
+			);
+		}
+	}
+
+	#[test]
+	fn test_truncate() {
+		assert_eq!(truncate("short", 10), "short");
+		assert_eq!(truncate("exactly", 7), "exactly");
+		assert_eq!(truncate("too long", 4), "too…");
+		assert_eq!(truncate("ünïcödé", 3), "ün…");
+	}
+
+	#[test]
+	fn test_progress_bar() {
+		assert_eq!(progress_bar(Duration::ZERO, Duration::ZERO, 0), "");
+		let total = Duration::from_secs(100);
+		assert_eq!(progress_bar(Duration::ZERO, total, 5), "🔘▬▬▬▬");
+		assert_eq!(progress_bar(Duration::from_secs(50), total, 5), "▬▬🔘▬▬");
+		assert_eq!(progress_bar(Duration::from_secs(200), total, 5), "▬▬▬▬🔘");
+	}
+
+	#[test]
+	fn test_parse_positions() {
+		assert_eq!(parse_positions("5-20"), Some(vec![5..=20]));
+		assert_eq!(parse_positions("3 5,7-8"), Some(vec![3..=3, 5..=5, 7..=8]));
+		assert_eq!(parse_positions("8-7"), None);
+		assert_eq!(parse_positions("two"), None);
+		assert_eq!(parse_positions(" "), None);
+	}
+
+	#[test]
+	fn test_without_playlist() {
+		assert_eq!(
+			without_playlist(
+				"https://www.youtube.com/watch?v=abc&list=PL123&index=2"
+			),
+			"https://www.youtube.com/watch?v=abc"
+		);
+		assert_eq!(
+			without_playlist("https://www.youtube.com/playlist?list=PL123"),
+			"https://www.youtube.com/playlist?list=PL123"
+		);
+		assert_eq!(without_playlist("never gonna"), "never gonna");
+	}
+
+	#[tokio::test]
+	async fn test_queue_songs_playlist() {
+		let resolver = Arc::new(playlist_resolver());
+		let queue = Mutex::new(Vec::new());
+
+		let message = queue_songs(
+			&queue,
+			PlayParameter::MaybeUrl(PLAYLIST.to_string()).get_tracks(resolver),
+			REQUESTER,
+			&GuildSettings::default(),
+			None,
+		)
+		.await
+		.unwrap();
+
+		assert_eq!(queue.lock().await.len(), 3);
+		assert!(message.contains("Track 1"));
+		assert!(message.contains("Added 3 song(s)"));
+		assert!(!message.contains("skipped"));
+	}
+
+	#[tokio::test]
+	async fn test_queue_songs_requester() {
+		let resolver = Arc::new(playlist_resolver());
+		let queue = Mutex::new(Vec::new());
+
+		queue_songs(
+			&queue,
+			PlayParameter::MaybeUrl(PLAYLIST.to_string()).get_tracks(resolver),
+			REQUESTER,
+			&GuildSettings::default(),
+			None,
+		)
+		.await
+		.unwrap();
+
+		let queue = queue.lock().await;
+		assert_eq!(queue.len(), 3);
+		for track in queue.iter() {
+			assert_eq!(get_requester(&track.handle).await, Some(REQUESTER));
+		}
+	}
+
+	#[tokio::test]
+	async fn test_queue_songs_partial_failure() {
+		let resolver =
+			Arc::new(playlist_resolver().with_failure("https://example.com/2"));
+		let queue = Mutex::new(Vec::new());
+		let (progress, updates) = watch::channel(Progress {
+			total: Some(3),
+			..Progress::default()
+		});
+
+		let message = queue_songs(
+			&queue,
+			PlayParameter::MaybeUrl(PLAYLIST.to_string()).get_tracks(resolver),
+			REQUESTER,
+			&GuildSettings::default(),
+			Some(&progress),
+		)
+		.await
+		.unwrap();
+
+		assert_eq!(queue.lock().await.len(), 2);
+		assert!(message.contains("Added 2 song(s)"));
+		assert!(message.contains("skipped"));
+		assert!(message.contains("2. Track 2"));
+
+		let progress = updates.borrow().clone();
+		assert_eq!(progress.total, Some(3));
+		assert_eq!(progress.loaded, 2);
+		assert_eq!(progress.failed, 1);
+		assert!(progress.to_string().starts_with("Loaded 2 of 3 song(s)"));
+		assert!(progress.to_string().ends_with("1 failed so far"));
+	}
+
+	#[tokio::test]
+	async fn test_queue_songs_full() {
+		let resolver = Arc::new(playlist_resolver());
+		let settings = GuildSettings {
+			max_queue: Some(2),
+			..GuildSettings::default()
+		};
+		let queue = Mutex::new(Vec::new());
+
+		let message = queue_songs(
+			&queue,
+			PlayParameter::MaybeUrl(PLAYLIST.to_string())
+				.get_tracks(resolver.clone()),
+			REQUESTER,
+			&settings,
+			None,
+		)
+		.await
+		.unwrap();
+
+		assert_eq!(queue.lock().await.len(), 2);
+		assert!(message.contains("The queue is full"));
+
+		let result = queue_songs(
+			&queue,
+			PlayParameter::MaybeUrl(PLAYLIST.to_string()).get_tracks(resolver),
+			REQUESTER,
+			&settings,
+			None,
+		)
+		.await;
+
+		assert!(matches!(result, Err(Error::QueueFull(2))));
+		assert_eq!(queue.lock().await.len(), 2);
+	}
+
+	#[tokio::test]
+	async fn test_queue_songs_cancelled() {
+		let resolver = Arc::new(playlist_resolver());
+		let queue = Mutex::new(Vec::new());
+
+		let message = queue_songs(
+			&queue,
+			PlayParameter::MaybeUrl(PLAYLIST.to_string())
+				.get_tracks(resolver)
+				.take(1)
+				.chain(stream::iter(vec![Err(Error::Cancelled)])),
+			REQUESTER,
+			&GuildSettings::default(),
+			None,
+		)
+		.await
+		.unwrap();
+
+		assert_eq!(queue.lock().await.len(), 1);
+		assert!(message.contains("Loading was cancelled"));
+
+		let result = queue_songs(
+			&queue,
+			stream::iter(vec![Err(Error::Cancelled)]),
+			REQUESTER,
+			&GuildSettings::default(),
+			None,
+		)
+		.await;
+
+		assert!(matches!(result, Err(Error::Cancelled)));
+		assert_eq!(queue.lock().await.len(), 1);
+	}
+
+	#[tokio::test]
+	async fn test_queue_songs_failure() {
+		let resolver = Arc::new(
+			FakeResolver::default().with_failure("https://example.com/1"),
+		);
+		let queue = Mutex::new(Vec::new());
+
+		let result = queue_songs(
+			&queue,
+			PlayParameter::Tracks(vec![TrackDescriptor::from_url(
+				"https://example.com/1",
+			)])
+			.get_tracks(resolver),
+			REQUESTER,
+			&GuildSettings::default(),
+			None,
+		)
+		.await;
+
+		assert!(matches!(result, Err(Error::Track(..))));
+		assert!(queue.lock().await.is_empty());
+	}
 }